@@ -0,0 +1,27 @@
+//! Compares deriving a multi-block PBKDF2 key one block after another with
+//! deriving the blocks in parallel. Run with `cargo bench --bench pbkdf2`.
+
+use std::{hint::black_box, time::Instant};
+
+use hackattic::password_hashing::{pbkdf2_sha256, pbkdf2_sha256_parallel, PBKDF2_BLOCK_LEN};
+
+const ROUNDS: u32 = 100_000;
+const BLOCKS: usize = 8;
+const RUNS: u32 = 5;
+
+fn time(name: &str, derive: fn(&[u8], &[u8], u32, &mut [u8])) {
+    let mut out = [0; BLOCKS * PBKDF2_BLOCK_LEN];
+    let started = Instant::now();
+    for _ in 0..RUNS {
+        derive(black_box(b"rosebud"), black_box(b"salt"), ROUNDS, &mut out);
+    }
+    println!(
+        "{name:>10}: {:?} per {BLOCKS} block key of {ROUNDS} rounds",
+        started.elapsed() / RUNS
+    );
+}
+
+fn main() {
+    time("sequential", pbkdf2_sha256);
+    time("parallel", pbkdf2_sha256_parallel);
+}
@@ -1,86 +1,1008 @@
 use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
     io::{Read, Write},
+    iter::Peekable,
+    path::PathBuf,
     process::{Command, Stdio},
+    str::Chars,
+    sync::{Mutex, OnceLock},
     time::Duration,
 };
 
-use base64::{engine::general_purpose, Engine};
+use anyhow::Context;
+use deadpool_postgres::{Manager, ManagerConfig, Object, Pool, RecyclingMethod};
 use flate2::read::GzDecoder;
 use serde::{Deserialize, Serialize};
-use tokio::time::sleep;
-use tokio_postgres::NoTls;
+use tokio_postgres::{
+    config::{Host, SslMode},
+    NoTls, Row,
+};
 
-use crate::Hackattic;
+use crate::{
+    config::ChallengeConfig,
+    encoding::{base64_decode, hex_encode},
+    hackattic_context::HackatticContext,
+    postgres_tls::MakeNativeTlsConnector,
+    query::query_rows,
+    wait::wait_until,
+    About, Hackattic,
+};
 
 pub struct BackupRestore;
 
+/// `{"dump": "<base64 gzipped pg_dump>"}`
 #[derive(Deserialize, Debug)]
 pub struct BackupRestoreProblem {
     dump: String,
+    /// Whether to sanity check the dump before loading it, from the config
+    /// file.
+    #[serde(skip)]
+    check_dump: bool,
+    /// How to reach Postgres, from the config file.
+    #[serde(skip)]
+    postgres: PostgresSettings,
+}
+
+/// `[challenges.backup_restore]` in the config file.
+#[derive(Deserialize, Debug, Default)]
+pub struct BackupRestoreConfig {
+    /// Fails early on a dump that doesn't look like a complete pg_dump.
+    /// Off by default, as the check knows only the dumps seen so far.
+    #[serde(default)]
+    pub check_dump: bool,
+    /// A libpq style `key=value` connection string, e.g.
+    /// `host=db.example.com user=hackattic password=... dbname=hackattic`,
+    /// for a server other than the local docker one.
+    pub connection: Option<String>,
+    #[serde(default)]
+    pub tls: TlsMode,
+    /// PEM root certificate the server's is checked against, for managed
+    /// instances with their own CA. The system roots otherwise, which psql
+    /// only understands from libpq 16 on.
+    pub tls_ca_file: Option<PathBuf>,
+}
+
+/// `tls` in the config file. The local docker Postgres has no TLS, managed
+/// ones often insist on it.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum TlsMode {
+    #[default]
+    Disable,
+    /// Only connects over TLS, with the server's certificate and name
+    /// verified.
+    Require,
 }
 
-#[derive(Serialize, Debug)]
+/// Where the dump is restored to and how it's reached, from the config file.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PostgresSettings {
+    pub connection: String,
+    pub mode: TlsMode,
+    pub ca_file: Option<PathBuf>,
+}
+
+impl Default for PostgresSettings {
+    fn default() -> Self {
+        PostgresSettings {
+            connection: CONNECTION.to_string(),
+            mode: TlsMode::default(),
+            ca_file: None,
+        }
+    }
+}
+
+impl From<&BackupRestoreConfig> for PostgresSettings {
+    fn from(config: &BackupRestoreConfig) -> Self {
+        PostgresSettings {
+            connection: config
+                .connection
+                .clone()
+                .unwrap_or_else(|| CONNECTION.to_string()),
+            mode: config.tls,
+            ca_file: config.tls_ca_file.clone(),
+        }
+    }
+}
+
+impl ChallengeConfig for BackupRestoreConfig {
+    const CHALLENGE: &'static str = BackupRestore::NAME;
+}
+
+#[derive(Serialize, Deserialize, Debug)]
 pub struct BackupRestoreAnswer {
     alive_ssns: Vec<String>,
 }
 
+impl BackupRestoreAnswer {
+    fn from_buckets(mut buckets: BTreeMap<&'static str, Vec<String>>) -> Self {
+        BackupRestoreAnswer {
+            alive_ssns: buckets.remove(ALIVE_SSNS).unwrap_or_default(),
+        }
+    }
+}
+
 impl Hackattic for BackupRestore {
     const NAME: &'static str = "backup_restore";
+    const ABOUT: About = About {
+        summary: "Restore a gzipped pg_dump and list the SSNs of everyone still alive.",
+        problem: r#"{"dump": "<base64 gzipped pg_dump>"}"#,
+        prerequisites: &[
+            "a Postgres server, on localhost:5432 with user postgres and password toor unless `connection` is configured",
+            "psql on the PATH, to load the dump",
+        ],
+    };
     type Problem = BackupRestoreProblem;
     type Answer = BackupRestoreAnswer;
 
+    fn answer_schema() -> Option<serde_json::Value> {
+        Some(serde_json::json!({
+            "type": "object",
+            "required": ["alive_ssns"],
+            "properties": {"alive_ssns": {"type": "array", "items": {"type": "string"}}}
+        }))
+    }
+
+    fn configure(problem: &mut Self::Problem, context: &HackatticContext) -> anyhow::Result<()> {
+        let config = context.challenge_config::<BackupRestoreConfig>()?;
+        problem.check_dump = config.check_dump;
+        problem.postgres = PostgresSettings::from(&config);
+        Ok(())
+    }
+
     // create a new postgres instance
     // docker run --name pg -p 5432:5432 -e POSTGRES_PASSWORD=toor -d postgres:10.21
     async fn solve(problem: Self::Problem) -> anyhow::Result<Self::Answer> {
         let sql_dump = get_uncompressed_sql_dump(&problem.dump)?;
-        write_dump_to_database(&sql_dump)?;
+        if problem.check_dump {
+            check_dump(&sql_dump)?;
+        }
+        if std::env::var_os(OFFLINE_ENV).is_some() {
+            return answer_from_dump(&sql_dump);
+        }
+        write_dump_to_database(&sql_dump, &problem.postgres)?;
 
-        sleep(Duration::from_millis(500)).await;
-
-        let (client, connection) =
-            tokio_postgres::connect("host=localhost user=postgres password=toor", NoTls).await?;
-
-        tokio::spawn(async move {
-            if let Err(e) = connection.await {
-                eprintln!("connection error: {}", e);
-            }
-        });
-
-        let res = client
-            .query(
-                "select ssn from criminal_records where status like 'alive'",
+        let columns = wait_until(READY_TIMEOUT, READY_INTERVAL, || async {
+            let client = connect(&problem.postgres).await?;
+            let columns = query_rows(
+                &client,
+                "select column_name::text from information_schema.columns \
+                 where table_name = 'criminal_records' order by ordinal_position",
                 &[],
+                |row| Ok(row.try_get::<_, String>(0)?),
             )
             .await?;
+            // retried until the restore has created the table
+            if columns.is_empty() {
+                anyhow::bail!("the dump did not create criminal_records");
+            }
+            Ok(columns)
+        })
+        .await
+        .context("restored table could not be queried")?;
+
+        let client = connect(&problem.postgres).await?;
+        let statement = match liveness(&columns)? {
+            Liveness::Status => "select ssn, status::text from criminal_records".to_string(),
+            Liveness::Flag(flag) => format!(
+                "select ssn, case when {flag} then '{FLAGGED_ALIVE}' end from criminal_records"
+            ),
+        };
+        let record = |row: &Row| Ok((row.try_get(0)?, row.try_get(1)?));
+        let records = query_rows(&client, &statement, &[], record)
+            .await
+            .context("failed to query records")?;
+
+        // the same answer `answer_from_dump` gives, even when no one is alive
+        Ok(BackupRestoreAnswer::from_buckets(bucket_by_status(
+            records, BUCKETS,
+        )))
+    }
+
+    async fn clean(context: &HackatticContext) -> anyhow::Result<()> {
+        let config = context.challenge_config::<BackupRestoreConfig>()?;
+        let client = connect(&PostgresSettings::from(&config)).await?;
+        client
+            .batch_execute(&format!(
+                "drop table if exists {}",
+                LOADED_TABLES.join(", ")
+            ))
+            .await
+            .context("failed to drop restored tables")?;
+        Ok(())
+    }
+}
+
+const READY_TIMEOUT: Duration = Duration::from_secs(10);
+const READY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Tables created by loading the dump, dropped again by `clean`.
+const LOADED_TABLES: &[&str] = &["criminal_records"];
+
+/// The local docker Postgres, see `solve`.
+const CONNECTION: &str = "host=localhost user=postgres password=toor";
+
+/// Shared across solves so loop mode and the regression runner don't
+/// reconnect every time. There is one per distinct settings, so a solve
+/// never lands on a server another configuration picked.
+fn pool(postgres: &PostgresSettings) -> anyhow::Result<Pool> {
+    static POOLS: OnceLock<Mutex<HashMap<PostgresSettings, Pool>>> = OnceLock::new();
+    let mut pools = POOLS
+        .get_or_init(Default::default)
+        .lock()
+        .expect("postgres pools lock poisoned");
+    if let Some(pool) = pools.get(postgres) {
+        return Ok(pool.clone());
+    }
+
+    let config = postgres_config(postgres)?;
+    let manager_config = ManagerConfig {
+        recycling_method: RecyclingMethod::Fast,
+    };
+    let manager = match tls_connector(postgres)? {
+        Some(connector) => Manager::from_config(config, connector, manager_config),
+        None => Manager::from_config(config, NoTls, manager_config),
+    };
+    let pool = Pool::builder(manager)
+        .max_size(4)
+        .build()
+        .context("failed to build postgres pool")?;
+    pools.insert(postgres.clone(), pool.clone());
+    Ok(pool)
+}
+
+fn postgres_config(postgres: &PostgresSettings) -> anyhow::Result<tokio_postgres::Config> {
+    let mut config: tokio_postgres::Config = postgres
+        .connection
+        .parse()
+        .context("invalid backup_restore connection")?;
+    if postgres.mode == TlsMode::Require {
+        // fails the connection, rather than falling back, if the server
+        // doesn't do TLS
+        config.ssl_mode(SslMode::Require);
+    }
+    Ok(config)
+}
+
+fn tls_connector(postgres: &PostgresSettings) -> anyhow::Result<Option<MakeNativeTlsConnector>> {
+    if postgres.mode == TlsMode::Disable {
+        return Ok(None);
+    }
+    let mut builder = native_tls::TlsConnector::builder();
+    if let Some(path) = &postgres.ca_file {
+        let pem = fs::read(path)
+            .with_context(|| format!("unable to read tls_ca_file {}", path.display()))?;
+        let certificate = native_tls::Certificate::from_pem(&pem)
+            .with_context(|| format!("tls_ca_file {} is not a PEM certificate", path.display()))?;
+        builder.add_root_certificate(certificate);
+    }
+    let connector = builder.build().context("failed to set up postgres TLS")?;
+    Ok(Some(MakeNativeTlsConnector::new(connector)))
+}
+
+async fn connect(postgres: &PostgresSettings) -> anyhow::Result<Object> {
+    pool(postgres)?
+        .get()
+        .await
+        .context("failed to get a postgres connection")
+}
+
+/// Why backup_restore can't be solved here with `context`'s settings: it
+/// needs psql and a Postgres to load the dump into.
+pub async fn missing_prerequisite(context: &HackatticContext) -> Option<String> {
+    let postgres = match context.challenge_config::<BackupRestoreConfig>() {
+        Ok(config) => PostgresSettings::from(&config),
+        Err(e) => return Some(format!("{e:#}")),
+    };
+    missing_prerequisite_with("psql", &postgres).await
+}
 
-        let ssns: Vec<String> = res.iter().map(|r| r.get("ssn")).collect();
-        Ok(BackupRestoreAnswer { alive_ssns: ssns })
+async fn missing_prerequisite_with(psql: &str, postgres: &PostgresSettings) -> Option<String> {
+    if let Err(e) = Command::new(psql).arg("--version").output() {
+        return Some(format!("{psql} can't be run: {e}"));
     }
+    connect(postgres).await.err().map(|e| format!("{e:#}"))
 }
 
-fn get_uncompressed_sql_dump(encoded: &str) -> anyhow::Result<String> {
-    let compressed_bytes = general_purpose::STANDARD.decode(encoded)?;
+/// How many leading bytes of an undecodable dump are shown.
+const DUMP_HEAD_LEN: usize = 16;
+
+/// The dump as raw bytes, it's only as UTF-8 as the data in it and psql gets
+/// it verbatim.
+fn get_uncompressed_sql_dump(encoded: &str) -> anyhow::Result<Vec<u8>> {
+    let compressed_bytes = base64_decode(encoded).context("dump is not base64")?;
     let mut decoder = GzDecoder::new(compressed_bytes.as_slice());
-    let mut s = String::new();
-    decoder.read_to_string(&mut s)?;
+    let mut s = Vec::new();
+    decoder.read_to_end(&mut s).with_context(|| {
+        let head = &compressed_bytes[..compressed_bytes.len().min(DUMP_HEAD_LEN)];
+        format!("dump is not gzipped sql, starts with {}", hex_encode(head))
+    })?;
     Ok(s)
 }
 
-fn write_dump_to_database(sql_dump: &str) -> anyhow::Result<()> {
-    let mut child = Command::new("psql")
-        .arg("-h")
-        .arg("localhost")
-        .arg("-U")
-        .arg("postgres")
-        .arg("-f")
-        .arg("-") // read file from stdin
-        .env("PGPASSWORD", "toor")
-        .stdin(Stdio::piped())
-        .spawn()?;
+/// A cheap look for what every pg_dump of the challenge has had: a table
+/// definition, and data in COPY blocks that end in `\.` or in INSERTs. A
+/// truncated download or a wrong decode fails here rather than as an
+/// empty answer.
+fn check_dump(sql_dump: &[u8]) -> anyhow::Result<()> {
+    let sql = String::from_utf8_lossy(sql_dump);
+    let (mut tables, mut has_data) = (0, false);
+    let mut open_copy = None;
+    for line in sql.lines() {
+        if open_copy.is_some() {
+            if line == "\\." {
+                open_copy = None;
+            }
+        } else if line.starts_with("CREATE TABLE ") {
+            tables += 1;
+        } else if line.starts_with("INSERT INTO ") {
+            has_data = true;
+        } else if let Some(copy) = line.strip_prefix("COPY ") {
+            has_data = true;
+            open_copy = copy.split_whitespace().next();
+        }
+    }
+
+    if let Some(table) = open_copy {
+        anyhow::bail!("the COPY into {table} never ends in \\., the dump looks truncated");
+    }
+    if tables == 0 {
+        anyhow::bail!("the dump has no CREATE TABLE, was it decoded right?");
+    }
+    if !has_data {
+        anyhow::bail!("the dump has {tables} table(s) but no COPY or INSERT data");
+    }
+    Ok(())
+}
+
+/// Set to read the dump's COPY data directly instead of restoring it into
+/// postgres.
+const OFFLINE_ENV: &str = "HA_RESTORE_OFFLINE";
+
+/// `status` values of a living person's record.
+const ALIVE_STATUSES: &[&str] = &["alive", "living"];
+
+/// The status given to records whose boolean column says they're alive.
+const FLAGGED_ALIVE: &str = "alive";
+
+const ALIVE_SSNS: &str = "alive_ssns";
+
+/// An answer field listing the SSNs of every record whose status is one of
+/// `statuses`.
+#[derive(Debug)]
+struct StatusBucket {
+    field: &'static str,
+    statuses: &'static [&'static str],
+}
+
+/// The answer fields the challenge asks for. A variant wanting more, say
+/// SSNs of those at large, needs another bucket and answer field.
+const BUCKETS: &[StatusBucket] = &[StatusBucket {
+    field: ALIVE_SSNS,
+    statuses: ALIVE_STATUSES,
+}];
+
+/// Sorts `(ssn, status)` records into each bucket's field, in record order.
+/// Every field is present, if only empty, and records without a status or
+/// with one no bucket takes are left out.
+fn bucket_by_status(
+    records: impl IntoIterator<Item = (String, Option<String>)>,
+    buckets: &[StatusBucket],
+) -> BTreeMap<&'static str, Vec<String>> {
+    let mut fields: BTreeMap<_, _> = buckets.iter().map(|b| (b.field, Vec::new())).collect();
+    for (ssn, status) in records {
+        let Some(status) = status else { continue };
+        for bucket in buckets {
+            if bucket.statuses.contains(&status.as_str()) {
+                fields.entry(bucket.field).or_default().push(ssn.clone());
+            }
+        }
+    }
+    fields
+}
+
+/// Boolean columns some dumps carry instead of a `status`.
+const ALIVE_FLAGS: &[&str] = &["alive", "is_alive"];
+
+/// How `criminal_records` says someone is alive.
+#[derive(Debug, PartialEq, Eq)]
+enum Liveness {
+    /// a text `status` in `ALIVE_STATUSES`
+    Status,
+    /// a boolean column
+    Flag(&'static str),
+}
+
+/// The solver assumes `criminal_records` has an `ssn` column and a
+/// `status`, or failing that one of `ALIVE_FLAGS`. Missing ones are named
+/// rather than answering with no one alive.
+fn liveness(columns: &[String]) -> anyhow::Result<Liveness> {
+    let has = |name: &str| columns.iter().any(|c| c == name);
+    let liveness = match has("status") {
+        true => Some(Liveness::Status),
+        false => ALIVE_FLAGS
+            .iter()
+            .find(|f| has(f))
+            .map(|&f| Liveness::Flag(f)),
+    };
+
+    let mut missing = Vec::new();
+    if !has("ssn") {
+        missing.push("ssn".to_string());
+    }
+    if liveness.is_none() {
+        missing.push(format!("status (or {})", ALIVE_FLAGS.join(", ")));
+    }
+    match liveness {
+        Some(liveness) if missing.is_empty() => Ok(liveness),
+        _ => anyhow::bail!(
+            "criminal_records has no {} column, only {}",
+            missing.join(" or "),
+            columns.join(", ")
+        ),
+    }
+}
+
+/// The pure Rust path: picks the alive SSNs out of the dump's COPY block.
+/// Bytes that aren't UTF-8 are replaced, they can only spoil columns other
+/// than the SSN and status.
+fn answer_from_dump(sql_dump: &[u8]) -> anyhow::Result<BackupRestoreAnswer> {
+    let buckets = bucket_by_status(records_from_dump(sql_dump)?, BUCKETS);
+    Ok(BackupRestoreAnswer::from_buckets(buckets))
+}
+
+/// `(ssn, status)` of every record in the dump's COPY data, a boolean
+/// column's true standing in for `FLAGGED_ALIVE`.
+fn records_from_dump(sql_dump: &[u8]) -> anyhow::Result<Vec<(String, Option<String>)>> {
+    let sql_dump = String::from_utf8_lossy(sql_dump);
+    let (columns, rows) = copy_rows(&sql_dump, "criminal_records")?;
+    let column = |name: &str| columns.iter().position(|c| c == name);
+    let liveness = liveness(&columns)?;
+    let status_column = match liveness {
+        Liveness::Status => column("status"),
+        Liveness::Flag(flag) => column(flag),
+    };
+    let (ssn, status_column) = (
+        column("ssn").expect("liveness checked"),
+        status_column.expect("liveness checked"),
+    );
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|row| {
+            let status = row.get(status_column).cloned().flatten();
+            let status = match liveness {
+                Liveness::Status => status,
+                Liveness::Flag(_) => status
+                    .filter(|v| matches!(v.as_str(), "t" | "true"))
+                    .map(|_| FLAGGED_ALIVE.to_string()),
+            };
+            Some((row.get(ssn).cloned().flatten()?, status))
+        })
+        .collect())
+}
+
+type CopyRow = Vec<Option<String>>;
+
+/// Column names and rows of the `COPY <table> (...) FROM stdin;` block.
+fn copy_rows(sql_dump: &str, table: &str) -> anyhow::Result<(Vec<String>, Vec<CopyRow>)> {
+    let mut lines = sql_dump.lines();
+    let columns = lines
+        .by_ref()
+        .find_map(|line| {
+            let rest = line.strip_prefix("COPY ")?;
+            let (name, rest) = rest.split_once(' ')?;
+            if name != table && !name.ends_with(&format!(".{table}")) {
+                return None;
+            }
+            let columns = rest.strip_prefix('(')?.split_once(')')?.0;
+            Some(columns.split(',').map(|c| c.trim().to_string()).collect())
+        })
+        .with_context(|| format!("dump has no COPY data for {table}"))?;
+
+    let rows = lines
+        .take_while(|line| *line != "\\.")
+        .map(parse_copy_line)
+        .collect();
+    Ok((columns, rows))
+}
+
+/// Splits a row of COPY text data on unescaped tabs and unescapes each field,
+/// `None` standing for `\N`.
+fn parse_copy_line(line: &str) -> CopyRow {
+    let mut fields = Vec::new();
+    let mut field = Vec::new();
+    let mut is_null = false;
+    let mut chars = line.chars().peekable();
 
-    let stdin = child.stdin.as_mut().unwrap();
-    stdin.write_all(sql_dump.as_bytes())?;
+    while let Some(c) = chars.next() {
+        match c {
+            '\t' => {
+                fields.push(finish_field(&mut field, &mut is_null));
+            }
+            '\\' => match chars.next() {
+                Some('N') if field.is_empty() && matches!(chars.peek(), None | Some('\t')) => {
+                    is_null = true
+                }
+                Some('b') => field.push(0x08),
+                Some('f') => field.push(0x0c),
+                Some('n') => field.push(b'\n'),
+                Some('r') => field.push(b'\r'),
+                Some('t') => field.push(b'\t'),
+                Some('v') => field.push(0x0b),
+                Some('x') => {
+                    let digits = take_digits(&mut chars, 16, 2);
+                    match u8::from_str_radix(&digits, 16) {
+                        Ok(byte) => field.push(byte),
+                        Err(_) => field.push(b'x'),
+                    }
+                }
+                Some(d @ '0'..='7') => {
+                    let digits = format!("{d}{}", take_digits(&mut chars, 8, 2));
+                    // three octal digits can exceed a byte, postgres keeps the low bits
+                    field.push(u32::from_str_radix(&digits, 8).unwrap_or(0) as u8);
+                }
+                Some(other) => push_char(&mut field, other),
+                None => field.push(b'\\'),
+            },
+            other => push_char(&mut field, other),
+        }
+    }
+    fields.push(finish_field(&mut field, &mut is_null));
+    fields
+}
+
+fn take_digits(chars: &mut Peekable<Chars>, radix: u32, max: usize) -> String {
+    let mut digits = String::new();
+    while digits.len() < max {
+        match chars.next_if(|c| c.is_digit(radix)) {
+            Some(c) => digits.push(c),
+            None => break,
+        }
+    }
+    digits
+}
+
+fn push_char(field: &mut Vec<u8>, c: char) {
+    field.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+}
+
+fn finish_field(field: &mut Vec<u8>, is_null: &mut bool) -> Option<String> {
+    let bytes = std::mem::take(field);
+    if std::mem::take(is_null) {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+fn write_dump_to_database(sql_dump: &[u8], postgres: &PostgresSettings) -> anyhow::Result<()> {
+    let mut command = Command::new("psql");
+    command.arg("-f").arg("-"); // read file from stdin
+    psql_env(&mut command, postgres)?;
+
+    pipe_to_command(command, sql_dump).context("failed to load dump with psql")
+}
+
+/// Points psql at the same server as the pool, through libpq's environment
+/// so the password stays out of the process list.
+fn psql_env(command: &mut Command, postgres: &PostgresSettings) -> anyhow::Result<()> {
+    let config = postgres_config(postgres)?;
+    match config.get_hosts().first() {
+        Some(Host::Tcp(host)) => {
+            command.env("PGHOST", host);
+        }
+        #[cfg(unix)]
+        Some(Host::Unix(socket_dir)) => {
+            command.env("PGHOST", socket_dir);
+        }
+        None => {}
+    }
+    if let Some(port) = config.get_ports().first() {
+        command.env("PGPORT", port.to_string());
+    }
+    if let Some(user) = config.get_user() {
+        command.env("PGUSER", user);
+    }
+    if let Some(password) = config.get_password() {
+        command.env("PGPASSWORD", String::from_utf8_lossy(password).as_ref());
+    }
+    if let Some(dbname) = config.get_dbname() {
+        command.env("PGDATABASE", dbname);
+    }
+    if postgres.mode == TlsMode::Require {
+        // verified as strictly as the pooled connections are. `system`
+        // needs libpq 16 or later, older ones want tls_ca_file
+        command.env("PGSSLMODE", "verify-full");
+        match &postgres.ca_file {
+            Some(path) => command.env("PGSSLROOTCERT", path),
+            None => command.env("PGSSLROOTCERT", "system"),
+        };
+    }
+    Ok(())
+}
 
-    stdin.flush()?;
+/// Runs `command` with `input` on its stdin and waits for it to exit.
+fn pipe_to_command(mut command: Command, input: &[u8]) -> anyhow::Result<()> {
+    let mut child = command.stdin(Stdio::piped()).spawn()?;
+
+    // dropping stdin closes the pipe, without the EOF the child waits forever
+    {
+        let mut stdin = child.stdin.take().context("child stdin was not captured")?;
+        stdin.write_all(input)?;
+        stdin.flush()?;
+    }
+
+    let status = child.wait()?;
+    if !status.success() {
+        anyhow::bail!("{:?} exited with {}", command.get_program(), status);
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::BTreeMap,
+        io::Write,
+        process::{Command, Stdio},
+    };
+
+    use base64::{engine::general_purpose, Engine};
+    use flate2::{write::GzEncoder, Compression};
+
+    use super::{
+        answer_from_dump, bucket_by_status, check_dump, connect, get_uncompressed_sql_dump,
+        missing_prerequisite_with, parse_copy_line, pipe_to_command, pool, postgres_config,
+        psql_env, tls_connector, BackupRestore, BackupRestoreConfig, BackupRestoreProblem,
+        PostgresSettings, StatusBucket, TlsMode, CONNECTION,
+    };
+    use crate::{config::Config, test_util, Hackattic, HackatticError};
+    use tokio_postgres::config::{Host, SslMode};
+
+    #[tokio::test]
+    async fn test_missing_psql_is_reported() {
+        // caught before any connection is tried
+        let reason = missing_prerequisite_with("no-such-psql", &PostgresSettings::default())
+            .await
+            .unwrap();
+        assert!(reason.starts_with("no-such-psql can't be run"), "{reason}");
+    }
+
+    #[test]
+    fn test_pools_are_kept_per_settings() {
+        let local = PostgresSettings::default();
+        let other = PostgresSettings {
+            connection: "host=db.example.com user=app".to_string(),
+            ..Default::default()
+        };
+        let (first, again, elsewhere) = (
+            pool(&local).unwrap(),
+            pool(&local).unwrap(),
+            pool(&other).unwrap(),
+        );
+        assert!(std::ptr::eq(first.manager(), again.manager()));
+        assert!(!std::ptr::eq(first.manager(), elsewhere.manager()));
+    }
+
+    #[test]
+    fn test_piped_child_completes() {
+        let mut command = Command::new("cat");
+        command.stdout(Stdio::null());
+        pipe_to_command(command, b"select 1;\n").unwrap();
+
+        let command = Command::new("false");
+        assert!(pipe_to_command(command, b"").is_err());
+    }
+
+    #[test]
+    fn test_configured_tls_selects_connector() {
+        let config = Config::parse("[challenges.backup_restore]\ntls = \"require\"").unwrap();
+        let tls = PostgresSettings::from(&config.challenge::<BackupRestoreConfig>().unwrap());
+        assert_eq!(tls.mode, TlsMode::Require);
+        assert!(tls_connector(&tls).unwrap().is_some());
+        assert_eq!(
+            postgres_config(&tls).unwrap().get_ssl_mode(),
+            SslMode::Require
+        );
+
+        let unset = PostgresSettings::from(&Config::default().challenge().unwrap());
+        assert_eq!(unset, PostgresSettings::default());
+        assert!(tls_connector(&unset).unwrap().is_none());
+        assert_ne!(
+            postgres_config(&unset).unwrap().get_ssl_mode(),
+            SslMode::Require
+        );
+
+        let missing_ca = PostgresSettings {
+            mode: TlsMode::Require,
+            ca_file: Some("/nonexistent/ca.pem".into()),
+            ..Default::default()
+        };
+        let err = tls_connector(&missing_ca).err().unwrap();
+        assert!(err.to_string().contains("tls_ca_file"), "{err}");
+    }
+
+    #[test]
+    fn test_configured_connection_reaches_pool_and_psql() {
+        let config = Config::parse(
+            "[challenges.backup_restore]\n\
+             connection = \"host=db.example.com port=6543 user=app password=s3cr3t dbname=ha\"\n\
+             tls = \"require\"",
+        )
+        .unwrap();
+        let postgres = PostgresSettings::from(&config.challenge::<BackupRestoreConfig>().unwrap());
+        let pool_config = postgres_config(&postgres).unwrap();
+        assert_eq!(
+            pool_config.get_hosts(),
+            [Host::Tcp("db.example.com".to_string())]
+        );
+        assert_eq!(pool_config.get_user(), Some("app"));
+
+        let mut command = Command::new("psql");
+        psql_env(&mut command, &postgres).unwrap();
+        let env: BTreeMap<_, _> = command
+            .get_envs()
+            .map(|(key, value)| (key.to_str().unwrap(), value.unwrap().to_str().unwrap()))
+            .collect();
+        assert_eq!(env["PGHOST"], "db.example.com");
+        assert_eq!(env["PGPORT"], "6543");
+        assert_eq!(env["PGUSER"], "app");
+        assert_eq!(env["PGPASSWORD"], "s3cr3t");
+        assert_eq!(env["PGDATABASE"], "ha");
+        assert_eq!(env["PGSSLMODE"], "verify-full");
+        assert!(command.get_args().all(|arg| arg != "s3cr3t"));
+
+        let unset = PostgresSettings::from(&Config::default().challenge().unwrap());
+        assert_eq!(unset.connection, CONNECTION);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_dump_is_solver_failure() {
+        let problem = BackupRestoreProblem {
+            dump: "not base64!".to_string(),
+            check_dump: false,
+            postgres: PostgresSettings::default(),
+        };
+
+        let err = BackupRestore::solve(problem).await.unwrap_err();
+        assert!(matches!(
+            HackatticError::from(err),
+            HackatticError::SolverFailed(_)
+        ));
+    }
+
+    fn gzip(sql: impl AsRef<[u8]>) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(sql.as_ref()).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_whitespace_laden_dump() {
+        let sql = "select 1;\n".repeat(20);
+        let encoded = general_purpose::STANDARD.encode(gzip(&sql));
+        let wrapped = encoded
+            .as_bytes()
+            .chunks(16)
+            .map(|line| std::str::from_utf8(line).unwrap())
+            .collect::<Vec<_>>()
+            .join("\r\n ");
+
+        assert_eq!(get_uncompressed_sql_dump(&wrapped).unwrap(), sql.as_bytes());
+    }
+
+    #[test]
+    fn test_url_safe_dump() {
+        let sql = "select '???>>>';\n";
+        let encoded = general_purpose::URL_SAFE_NO_PAD.encode(gzip(sql));
+
+        assert_eq!(get_uncompressed_sql_dump(&encoded).unwrap(), sql.as_bytes());
+    }
+
+    /// A LATIN1 dump, the names aren't valid UTF-8.
+    const LATIN1_DUMP: &[u8] = b"\
+SET client_encoding = 'LATIN1';
+CREATE TABLE criminal_records (id integer, name text, ssn text, status text);
+COPY criminal_records (id, name, ssn, status) FROM stdin;
+1\tJos\xe9\t123-45-6789\talive
+2\tZo\xeb\t987-65-4321\tdeceased
+\\.
+";
+
+    #[test]
+    fn test_truncated_dump_fails_check() {
+        check_dump(LATIN1_DUMP).unwrap();
+
+        let truncated = &LATIN1_DUMP[..LATIN1_DUMP.len() - 4];
+        let err = check_dump(truncated).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "the COPY into criminal_records never ends in \\., the dump looks truncated"
+        );
+
+        let err = check_dump(b"\x1f\x8b garbage").unwrap_err();
+        assert!(err.to_string().contains("no CREATE TABLE"), "{err}");
+    }
+
+    #[test]
+    fn test_non_utf8_dump_loads() {
+        let encoded = general_purpose::STANDARD.encode(gzip(LATIN1_DUMP));
+
+        let dump = get_uncompressed_sql_dump(&encoded).unwrap();
+        assert_eq!(dump, LATIN1_DUMP);
+        assert_eq!(answer_from_dump(&dump).unwrap().alive_ssns, ["123-45-6789"]);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a local postgres"]
+    async fn test_non_utf8_dump_restores() {
+        let problem = BackupRestoreProblem {
+            dump: general_purpose::STANDARD.encode(gzip(LATIN1_DUMP)),
+            check_dump: true,
+            postgres: PostgresSettings::default(),
+        };
+        let context = test_util::context("", Config::default());
+        BackupRestore::clean(&context).await.unwrap();
+
+        let answer = BackupRestore::solve(problem).await.unwrap();
+        assert_eq!(answer.alive_ssns, ["123-45-6789"]);
+        BackupRestore::clean(&context).await.unwrap();
+    }
+
+    #[test]
+    fn test_undecompressable_dump_shows_head() {
+        let encoded = general_purpose::STANDARD.encode(b"PK\x03\x04 not gzip");
+
+        let err = get_uncompressed_sql_dump(&encoded).unwrap_err();
+        assert!(
+            err.to_string().contains("504b0304206e6f7420677a6970"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_copy_line_escapes() {
+        let line = "1\tTab\\there\tback\\\\slash\t\\N\tline\\nbreak\t\\x41\\101";
+        assert_eq!(
+            parse_copy_line(line),
+            [
+                Some("1".to_string()),
+                Some("Tab\there".to_string()),
+                Some("back\\slash".to_string()),
+                None,
+                Some("line\nbreak".to_string()),
+                Some("AA".to_string()),
+            ]
+        );
+
+        // only a whole field of \N is null
+        assert_eq!(
+            parse_copy_line("\\Nope\t\\\\N\t"),
+            [
+                Some("Nope".to_string()),
+                Some("\\N".to_string()),
+                Some(String::new())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_records_are_bucketed_by_status() {
+        const BUCKETS: &[StatusBucket] = &[
+            StatusBucket {
+                field: "alive_ssns",
+                statuses: &["alive", "living"],
+            },
+            StatusBucket {
+                field: "at_large_ssns",
+                statuses: &["at large", "escaped"],
+            },
+            StatusBucket {
+                field: "wanted_ssns",
+                statuses: &["wanted"],
+            },
+        ];
+        let records = [
+            ("111", Some("alive")),
+            ("222", Some("at large")),
+            ("333", Some("deceased")),
+            ("444", Some("living")),
+            ("555", None),
+            ("666", Some("escaped")),
+        ]
+        .map(|(ssn, status)| (ssn.to_string(), status.map(str::to_string)));
+
+        let buckets = bucket_by_status(records, BUCKETS);
+        assert_eq!(buckets["alive_ssns"], ["111", "444"]);
+        assert_eq!(buckets["at_large_ssns"], ["222", "666"]);
+        assert!(buckets["wanted_ssns"].is_empty());
+        assert_eq!(buckets.len(), 3);
+    }
+
+    #[test]
+    fn test_alive_ssns_from_dump() {
+        let dump = "\
+CREATE TABLE public.criminal_records (id integer, ssn text, status text);
+COPY public.criminal_records (id, ssn, status) FROM stdin;
+1\t123-45-6789\talive
+2\t\\N\talive
+3\t987-65-4321\tdeceased
+4\t555\\t12\\\\3456\talive
+\\.
+";
+        assert_eq!(
+            answer_from_dump(dump.as_bytes()).unwrap().alive_ssns,
+            ["123-45-6789", "555\t12\\3456"]
+        );
+        assert!(answer_from_dump(b"select 1;").is_err());
+    }
+
+    #[test]
+    fn test_alive_flag_and_living_status() {
+        let dump = "\
+COPY criminal_records (ssn, is_alive) FROM stdin;
+111\tt
+222\tf
+\\.
+";
+        assert_eq!(
+            answer_from_dump(dump.as_bytes()).unwrap().alive_ssns,
+            ["111"]
+        );
+
+        let dump = "COPY criminal_records (ssn, status) FROM stdin;\n333\tliving\n\\.\n";
+        assert_eq!(
+            answer_from_dump(dump.as_bytes()).unwrap().alive_ssns,
+            ["333"]
+        );
+    }
+
+    #[test]
+    fn test_missing_columns_are_named() {
+        let dump = "COPY criminal_records (id, name) FROM stdin;\n1\tJosé\n\\.\n";
+        let err = answer_from_dump(dump.as_bytes()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "criminal_records has no ssn or status (or alive, is_alive) column, only id, name"
+        );
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a local postgres"]
+    async fn test_clean_drops_restored_table() {
+        let fixture = std::fs::read_to_string("fixtures/backup_restore.json").unwrap();
+        let fixture: serde_json::Value = serde_json::from_str(&fixture).unwrap();
+        let problem = serde_json::from_value(fixture["problem"].clone()).unwrap();
+        BackupRestore::solve(problem).await.unwrap();
+
+        BackupRestore::clean(&test_util::context("", Config::default()))
+            .await
+            .unwrap();
+
+        let client = connect(&PostgresSettings::default()).await.unwrap();
+        let row = client
+            .query_one("select to_regclass('criminal_records') is null", &[])
+            .await
+            .unwrap();
+        assert!(row.get::<_, bool>(0));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a local postgres"]
+    async fn test_sequential_solves_share_a_connection() {
+        let fixture = std::fs::read_to_string("fixtures/backup_restore.json").unwrap();
+        let fixture: serde_json::Value = serde_json::from_str(&fixture).unwrap();
+
+        for _ in 0..2 {
+            let problem = serde_json::from_value(fixture["problem"].clone()).unwrap();
+            BackupRestore::solve(problem).await.unwrap();
+            BackupRestore::clean(&test_util::context("", Config::default()))
+                .await
+                .unwrap();
+        }
+
+        let status = pool(&PostgresSettings::default()).unwrap().status();
+        assert_eq!(status.size, 1, "{status:?}");
+    }
+}
@@ -0,0 +1,242 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt,
+    future::Future,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use serde::Deserialize;
+use tokio::{
+    sync::Semaphore,
+    task::{JoinError, JoinSet},
+};
+
+use crate::HackatticError;
+
+/// Outcome of one challenge in a batch.
+#[derive(Debug)]
+pub struct BatchEntry {
+    pub name: String,
+    pub outcome: Result<String, HackatticError>,
+    pub elapsed: Duration,
+}
+
+/// Results of a batch, in the order the challenges were listed.
+#[derive(Debug)]
+pub struct Summary {
+    pub entries: Vec<BatchEntry>,
+    pub total: Duration,
+}
+
+impl Summary {
+    pub fn failed(&self) -> usize {
+        self.entries.iter().filter(|e| e.outcome.is_err()).count()
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.entries {
+            match &entry.outcome {
+                Ok(_) => writeln!(f, "{}: ok ({:.2?})", entry.name, entry.elapsed)?,
+                Err(e) => writeln!(f, "{}: failed: {} ({:.2?})", entry.name, e, entry.elapsed)?,
+            }
+        }
+        write!(f, "total: {:.2?}", self.total)
+    }
+}
+
+/// The `[batch]` section of the config file.
+#[derive(Deserialize, Debug, Default)]
+pub struct BatchConfig {
+    /// Retries allowed across the whole batch, unlimited if unset.
+    pub retry_budget: Option<u32>,
+}
+
+/// Retries left for a whole run, shared by every solve in it so that many
+/// challenges retrying against a struggling server don't add up to a flood.
+/// Spent retries are never given back.
+#[derive(Debug)]
+pub struct RetryBudget {
+    left: AtomicU32,
+}
+
+impl RetryBudget {
+    pub fn new(retries: u32) -> Self {
+        RetryBudget {
+            left: AtomicU32::new(retries),
+        }
+    }
+
+    /// Takes one retry, or returns false if none are left.
+    pub fn try_take(&self) -> bool {
+        self.left
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |left| {
+                left.checked_sub(1)
+            })
+            .is_ok()
+    }
+
+    pub fn left(&self) -> u32 {
+        self.left.load(Ordering::Relaxed)
+    }
+}
+
+/// One solve per distinct challenge, so repeats of a challenge queue up
+/// behind each other rather than compete for its local services.
+pub fn default_concurrency(names: &[String]) -> usize {
+    names.iter().collect::<HashSet<_>>().len().max(1)
+}
+
+/// Solves the challenges with at most `concurrency` running at once,
+/// reporting them in `names` order no matter which finishes first.
+pub async fn solve_all<F, Fut>(names: Vec<String>, concurrency: usize, solve_one: F) -> Summary
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<String, HackatticError>> + Send + 'static,
+{
+    let start = Instant::now();
+    let permits = Arc::new(Semaphore::new(concurrency.max(1)));
+    let mut set = JoinSet::new();
+    for (index, name) in names.iter().enumerate() {
+        let fut = solve_one(name.clone());
+        let permits = Arc::clone(&permits);
+        set.spawn(async move {
+            let _permit = permits
+                .acquire_owned()
+                .await
+                .expect("semaphore is never closed");
+            let started = Instant::now();
+            // a panicking solver fails its own challenge, not the whole batch
+            let outcome = tokio::spawn(fut)
+                .await
+                .unwrap_or_else(|e| Err(HackatticError::SolverFailed(panic_error(e))));
+            (index, outcome, started.elapsed())
+        });
+    }
+
+    let mut finished = BTreeMap::new();
+    while let Some(joined) = set.join_next().await {
+        let (index, outcome, elapsed) = joined.expect("solver panics are caught inside the task");
+        finished.insert(index, (outcome, elapsed));
+    }
+
+    let entries = names
+        .into_iter()
+        .zip(finished.into_values())
+        .map(|(name, (outcome, elapsed))| BatchEntry {
+            name,
+            outcome,
+            elapsed,
+        })
+        .collect();
+
+    Summary {
+        entries,
+        total: start.elapsed(),
+    }
+}
+
+fn panic_error(err: JoinError) -> anyhow::Error {
+    let Ok(payload) = err.try_into_panic() else {
+        return anyhow::anyhow!("solver task was cancelled");
+    };
+    match payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+    {
+        Some(message) => anyhow::anyhow!("solver panicked: {message}"),
+        None => anyhow::anyhow!("solver panicked"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use super::{default_concurrency, solve_all};
+    use crate::HackatticError;
+
+    #[tokio::test]
+    async fn test_summary_follows_input_order() {
+        let names = vec!["slow".to_string(), "fast".to_string()];
+        let summary = solve_all(names, 2, |name| async move {
+            if name == "slow" {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                Ok("passed".to_string())
+            } else {
+                Err(HackatticError::Rejected("wrong".to_string()))
+            }
+        })
+        .await;
+
+        let order: Vec<_> = summary.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(order, ["slow", "fast"]);
+        assert_eq!(summary.failed(), 1);
+
+        let printed = summary.to_string();
+        let lines: Vec<_> = printed.lines().collect();
+        assert!(lines[0].starts_with("slow: ok"), "{printed}");
+        assert!(
+            lines[1].starts_with("fast: failed: answer rejected: wrong"),
+            "{printed}"
+        );
+        assert!(lines[2].starts_with("total: "), "{printed}");
+    }
+
+    #[tokio::test]
+    async fn test_panicking_solver_fails_only_its_challenge() {
+        let names = vec!["panics".to_string(), "passes".to_string()];
+        let summary = solve_all(names, 2, |name| async move {
+            if name == "panics" {
+                panic!("index out of bounds");
+            }
+            Ok("passed".to_string())
+        })
+        .await;
+
+        assert_eq!(summary.failed(), 1);
+        let printed = summary.to_string();
+        assert!(
+            printed
+                .starts_with("panics: failed: solver failed: solver panicked: index out of bounds"),
+            "{printed}"
+        );
+        assert!(printed.contains("passes: ok"), "{printed}");
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_is_bounded() {
+        let names: Vec<_> = (0..6).map(|n| format!("challenge_{n}")).collect();
+        assert_eq!(default_concurrency(&names), 6);
+        assert_eq!(default_concurrency(&["a".to_string(), "a".to_string()]), 1);
+
+        let running = Arc::new(AtomicUsize::new(0));
+        let most = Arc::new(AtomicUsize::new(0));
+        let summary = solve_all(names, 2, |_| {
+            let (running, most) = (Arc::clone(&running), Arc::clone(&most));
+            async move {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                most.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                running.fetch_sub(1, Ordering::SeqCst);
+                Ok("passed".to_string())
+            }
+        })
+        .await;
+
+        assert_eq!(summary.failed(), 0);
+        assert_eq!(most.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,394 @@
+use std::{fs, io::ErrorKind, ops::RangeInclusive, path::Path};
+
+use anyhow::{Context, Result};
+use rand::Rng;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::progress::Progress;
+
+/// Character sets candidates are drawn from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Charset {
+    Digits,
+    Lowercase,
+    Uppercase,
+    Alphanumeric,
+}
+
+impl Charset {
+    pub fn chars(&self) -> &'static [u8] {
+        match self {
+            Charset::Digits => b"0123456789",
+            Charset::Lowercase => b"abcdefghijklmnopqrstuvwxyz",
+            Charset::Uppercase => b"ABCDEFGHIJKLMNOPQRSTUVWXYZ",
+            Charset::Alphanumeric => {
+                b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ"
+            }
+        }
+    }
+}
+
+/// Every string over a charset with a length in the given range, ordered
+/// shortest first. Candidates are addressed by index so they can be
+/// generated lazily and split across threads, which limits a keyspace to
+/// 2^64 candidates.
+#[derive(Debug, Clone)]
+pub struct Keyspace {
+    charset: &'static [u8],
+    lengths: RangeInclusive<usize>,
+    len: u64,
+    progress: Progress,
+}
+
+impl Keyspace {
+    /// Fails for keyspaces with more candidates than a `u64` can index.
+    pub fn new(charset: Charset, lengths: RangeInclusive<usize>) -> Result<Self> {
+        let base = charset.chars().len() as u64;
+        let len = lengths
+            .clone()
+            .try_fold(0u64, |total, len| {
+                let count = base.checked_pow(u32::try_from(len).ok()?)?;
+                total.checked_add(count)
+            })
+            .with_context(|| {
+                format!("{charset:?} strings of lengths {lengths:?} are too many to index")
+            })?;
+        Ok(Keyspace {
+            charset: charset.chars(),
+            lengths,
+            len,
+            progress: Progress::default(),
+        })
+    }
+
+    /// Reports checked candidates of `find_resumable` searches to `progress`,
+    /// which also stops them between chunks when cancelled.
+    pub fn with_progress(mut self, progress: Progress) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Number of candidates in the keyspace.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The candidate at `index`, or `None` past the end of the keyspace.
+    pub fn candidate(&self, mut index: u64) -> Option<String> {
+        let base = self.charset.len() as u64;
+        for len in self.lengths.clone() {
+            // every count fits, `new` checked their sum does
+            let count = base.checked_pow(len as u32)?;
+            if index < count {
+                let mut bytes = vec![self.charset[0]; len];
+                for byte in bytes.iter_mut().rev() {
+                    *byte = self.charset[(index % base) as usize];
+                    index /= base;
+                }
+                return Some(String::from_utf8(bytes).expect("charset is ascii"));
+            }
+            index -= count;
+        }
+        None
+    }
+
+    /// The charset and lengths, which tell keyspaces apart even when they
+    /// hold as many candidates.
+    fn fingerprint(&self) -> String {
+        let charset = std::str::from_utf8(self.charset).expect("charset is ascii");
+        format!("{charset} {:?}", self.lengths)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = String> + '_ {
+        (0..self.len()).map_while(|index| self.candidate(index))
+    }
+
+    /// Endless candidates drawn uniformly at random, repeats included, for
+    /// searches where likely answers aren't clustered at the start.
+    pub fn sample<'a, R: Rng>(&'a self, rng: &'a mut R) -> impl Iterator<Item = String> + 'a {
+        let len = self.len();
+        std::iter::from_fn(move || (len > 0).then(|| rng.gen_range(0..len)))
+            .map_while(|index| self.candidate(index))
+    }
+
+    /// Checks candidates in parallel, returning a matching one as soon as it
+    /// is found.
+    pub fn find<F>(&self, check: F) -> Option<String>
+    where
+        F: Fn(&str) -> bool + Sync,
+    {
+        (0..self.len())
+            .into_par_iter()
+            .filter_map(|index| self.candidate(index))
+            .find_any(|candidate| check(candidate))
+    }
+
+    /// Like `find`, but records how far the search got in `checkpoint` so
+    /// an interrupted search picks up there instead of from scratch. The
+    /// checkpoint is removed once the search is over, found or not, and kept
+    /// when it is cancelled.
+    pub fn find_resumable<F>(&self, checkpoint: &Path, check: F) -> Result<Option<String>>
+    where
+        F: Fn(&str) -> bool + Sync,
+    {
+        self.find_in_chunks(checkpoint, CHECKPOINT_CHUNK, check)
+    }
+
+    fn find_in_chunks<F>(&self, checkpoint: &Path, chunk: u64, check: F) -> Result<Option<String>>
+    where
+        F: Fn(&str) -> bool + Sync,
+    {
+        let len = self.len();
+        let keyspace = self.fingerprint();
+        let mut start = match Checkpoint::load(checkpoint)? {
+            Some(saved) if saved.keyspace == keyspace => {
+                info!("resuming from candidate {} of {len}", saved.next);
+                saved.next
+            }
+            Some(saved) => {
+                warn!(
+                    "{} is for keyspace {:?}, not {keyspace:?}, starting over",
+                    checkpoint.display(),
+                    saved.keyspace
+                );
+                0
+            }
+            None => 0,
+        };
+
+        // each search counts afresh, from where it resumed
+        self.progress.set_total(len);
+        self.progress.set_done(start);
+        while start < len {
+            self.progress.check()?;
+            let end = start.saturating_add(chunk).min(len);
+            let found = (start..end)
+                .into_par_iter()
+                .filter_map(|index| self.candidate(index))
+                .find_any(|candidate| check(candidate));
+            if found.is_some() {
+                Checkpoint::clear(checkpoint)?;
+                return Ok(found);
+            }
+            self.progress.advance(end - start);
+            start = end;
+            Checkpoint {
+                next: start,
+                keyspace: keyspace.clone(),
+            }
+            .save(checkpoint)?;
+        }
+        Checkpoint::clear(checkpoint)?;
+        Ok(None)
+    }
+}
+
+/// Candidates checked between checkpoint writes.
+const CHECKPOINT_CHUNK: u64 = 1 << 20;
+
+/// Where a `find_resumable` search stopped. The keyspace is ordered, so an
+/// index is all it takes to resume.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct Checkpoint {
+    /// The first candidate not yet checked.
+    pub next: u64,
+    /// The searched keyspace's charset and lengths, guarding against resuming
+    /// a different one. Empty in checkpoints that predate it, which are
+    /// started over.
+    #[serde(default)]
+    pub keyspace: String,
+}
+
+impl Checkpoint {
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        match fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json)
+                .map(Some)
+                .with_context(|| format!("{} is not a brute force checkpoint", path.display())),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("unable to read {}", path.display())),
+        }
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string(self).context("Unable to serialize")?;
+        fs::write(path, json).with_context(|| format!("unable to save {}", path.display()))
+    }
+
+    fn clear(path: &Path) -> Result<()> {
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => {
+                Err(e).with_context(|| format!("unable to remove {}", path.display()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use super::{Charset, Checkpoint, Keyspace};
+    use crate::{progress::Progress, SolveOptions};
+
+    #[test]
+    fn test_keyspace_size() {
+        assert_eq!(
+            Keyspace::new(Charset::Digits, 1..=3).unwrap().len(),
+            10 + 100 + 1000
+        );
+        assert_eq!(
+            Keyspace::new(Charset::Lowercase, 2..=2).unwrap().len(),
+            26 * 26
+        );
+        assert_eq!(
+            Keyspace::new(Charset::Alphanumeric, 1..=1).unwrap().len(),
+            62
+        );
+
+        let keyspace = Keyspace::new(Charset::Lowercase, 1..=2).unwrap();
+        assert_eq!(keyspace.iter().count() as u64, keyspace.len());
+    }
+
+    #[test]
+    fn test_oversized_keyspace_is_rejected() {
+        // 62^10 fits in a u64, 62^11 doesn't
+        assert!(Keyspace::new(Charset::Alphanumeric, 1..=10).is_ok());
+        let err = Keyspace::new(Charset::Alphanumeric, 8..=11).unwrap_err();
+        assert!(err.to_string().contains("too many to index"), "{err}");
+        assert!(Keyspace::new(Charset::Digits, 19..=usize::MAX).is_err());
+    }
+
+    #[test]
+    fn test_candidate_order() {
+        let keyspace = Keyspace::new(Charset::Digits, 1..=2).unwrap();
+        let candidates: Vec<_> = keyspace.iter().collect();
+        assert_eq!(candidates[0], "0");
+        assert_eq!(candidates[9], "9");
+        assert_eq!(candidates[10], "00");
+        assert_eq!(candidates[109], "99");
+        assert_eq!(keyspace.candidate(110), None);
+    }
+
+    #[test]
+    fn test_find_stops_early() {
+        let keyspace = Keyspace::new(Charset::Digits, 1..=6).unwrap();
+        let checked = AtomicU64::new(0);
+
+        let found = keyspace.find(|candidate| {
+            checked.fetch_add(1, Ordering::Relaxed);
+            candidate == "42"
+        });
+
+        assert_eq!(found.as_deref(), Some("42"));
+        assert!(checked.load(Ordering::Relaxed) < keyspace.len() / 2);
+    }
+
+    #[test]
+    fn test_seeded_samples_repeat() {
+        let keyspace = Keyspace::new(Charset::Alphanumeric, 4..=8).unwrap();
+        let options = SolveOptions {
+            seed: Some(42),
+            ..Default::default()
+        };
+
+        let first: Vec<_> = keyspace.sample(&mut options.rng()).take(50).collect();
+        let second: Vec<_> = keyspace.sample(&mut options.rng()).take(50).collect();
+        assert_eq!(first, second);
+
+        let other = SolveOptions {
+            seed: Some(43),
+            ..Default::default()
+        };
+        let third: Vec<_> = keyspace.sample(&mut other.rng()).take(50).collect();
+        assert_ne!(first, third);
+    }
+
+    #[test]
+    fn test_progress_restarts_with_each_search() {
+        let progress = Progress::new();
+        let keyspace = Keyspace::new(Charset::Digits, 1..=3)
+            .unwrap()
+            .with_progress(progress.clone());
+        let checkpoint =
+            std::env::temp_dir().join(format!("bruteforce-progress-{}.json", std::process::id()));
+
+        for _ in 0..2 {
+            let never = keyspace
+                .find_in_chunks(&checkpoint, 100, |_| false)
+                .unwrap();
+            assert_eq!(never, None);
+            assert_eq!(progress.position(), (1110, Some(1110)));
+        }
+    }
+
+    #[test]
+    fn test_restart_resumes_past_checkpoint() {
+        let keyspace = Keyspace::new(Charset::Digits, 1..=4).unwrap();
+        let checkpoint =
+            std::env::temp_dir().join(format!("bruteforce-{}.json", std::process::id()));
+        // "0042" is candidate 1152, "9999" the last
+        let before_interruption = Checkpoint {
+            next: 1200,
+            keyspace: keyspace.fingerprint(),
+        };
+        std::fs::write(
+            &checkpoint,
+            serde_json::to_string(&before_interruption).unwrap(),
+        )
+        .unwrap();
+
+        let lowest = AtomicU64::new(u64::MAX);
+        let check = |candidate: &str| {
+            // candidates of each length follow all shorter ones
+            let shorter = [0, 10, 110, 1110][candidate.len() - 1];
+            let index = shorter + candidate.parse::<u64>().unwrap();
+            lowest.fetch_min(index, Ordering::Relaxed);
+            candidate == "0042" || candidate == "9999"
+        };
+        let found = keyspace.find_in_chunks(&checkpoint, 100, check).unwrap();
+
+        assert_eq!(found.as_deref(), Some("9999"));
+        assert_eq!(lowest.load(Ordering::Relaxed), 1200);
+        assert_eq!(Checkpoint::load(&checkpoint).unwrap(), None);
+
+        // nor is one left behind once the keyspace is exhausted
+        let never = keyspace
+            .find_in_chunks(&checkpoint, 100, |_| false)
+            .unwrap();
+        assert_eq!(never, None);
+        assert!(!checkpoint.exists());
+    }
+
+    #[test]
+    fn test_checkpoint_of_another_keyspace_starts_over() {
+        let lowercase = Keyspace::new(Charset::Lowercase, 1..=2).unwrap();
+        let uppercase = Keyspace::new(Charset::Uppercase, 1..=2).unwrap();
+        assert_eq!(lowercase.len(), uppercase.len());
+        let checkpoint =
+            std::env::temp_dir().join(format!("bruteforce-other-{}.json", std::process::id()));
+        let lowercase_progress = Checkpoint {
+            next: 600,
+            keyspace: lowercase.fingerprint(),
+        };
+        std::fs::write(
+            &checkpoint,
+            serde_json::to_string(&lowercase_progress).unwrap(),
+        )
+        .unwrap();
+
+        // "B" is candidate 1, long before where the lowercase search stopped
+        let found = uppercase
+            .find_in_chunks(&checkpoint, 100, |candidate| candidate == "B")
+            .unwrap();
+        assert_eq!(found.as_deref(), Some("B"));
+        assert!(!checkpoint.exists());
+    }
+}
@@ -0,0 +1,92 @@
+use std::{
+    fs,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{Context, Result};
+use reqwest::{Request, StatusCode};
+use serde::{Deserialize, Serialize};
+
+/// Stands in for the access token wherever it appeared in a recording.
+pub const REDACTED_TOKEN: &str = "<access_token>";
+
+/// One request and the response it got, as recorded by `Cassette`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Interaction {
+    pub method: String,
+    pub path: String,
+    /// The query string, access token redacted.
+    pub query: Option<String>,
+    /// `None` for requests without a body or with a binary (e.g. gzipped) one.
+    pub request_body: Option<String>,
+    pub status: u16,
+    pub response_body: String,
+}
+
+/// Every request a solve sends and its response, in order, written out to
+/// a file so the exchange can be replayed against a mock server offline.
+/// Clones share one recording.
+#[derive(Debug, Clone, Default)]
+pub struct Cassette {
+    interactions: Arc<Mutex<Vec<Interaction>>>,
+}
+
+impl Cassette {
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = fs::read_to_string(path)
+            .with_context(|| format!("unable to read cassette {}", path.display()))?;
+        let interactions = serde_json::from_str(&json)
+            .with_context(|| format!("unable to parse cassette {}", path.display()))?;
+        Ok(Cassette {
+            interactions: Arc::new(Mutex::new(interactions)),
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(&self.interactions())?;
+        fs::write(path, json).with_context(|| format!("unable to save {}", path.display()))
+    }
+
+    pub fn interactions(&self) -> Vec<Interaction> {
+        self.interactions.lock().unwrap().clone()
+    }
+
+    /// Adds `request` and its response, with the access token replaced by
+    /// `REDACTED_TOKEN` in the query and anywhere it shows up in the bodies.
+    pub fn record(&self, request: &Request, status: StatusCode, response_body: &str) {
+        let mut url = request.url().clone();
+        let mut token = None;
+        let pairs: Vec<_> = url
+            .query_pairs()
+            .map(|(key, value)| match key.as_ref() {
+                "access_token" => {
+                    token = Some(value.into_owned()).filter(|token| !token.is_empty());
+                    (key.into_owned(), REDACTED_TOKEN.to_string())
+                }
+                _ => (key.into_owned(), value.into_owned()),
+            })
+            .collect();
+        if !pairs.is_empty() {
+            url.query_pairs_mut().clear().extend_pairs(pairs);
+        }
+        let redact = |text: &str| match &token {
+            Some(token) => text.replace(token.as_str(), REDACTED_TOKEN),
+            None => text.to_string(),
+        };
+
+        let interaction = Interaction {
+            method: request.method().to_string(),
+            path: url.path().to_string(),
+            query: url.query().map(str::to_string),
+            request_body: request
+                .body()
+                .and_then(|body| body.as_bytes())
+                .and_then(|body| std::str::from_utf8(body).ok())
+                .map(redact),
+            status: status.as_u16(),
+            response_body: redact(response_body),
+        };
+        self.interactions.lock().unwrap().push(interaction);
+    }
+}
@@ -0,0 +1,66 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use anyhow::{Context, Result};
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::batch::BatchConfig;
+
+/// Read when `HA_CONFIG` isn't set, if it exists.
+pub const DEFAULT_CONFIG_PATH: &str = "hackattic.toml";
+
+/// The optional TOML config file. Challenge specific knobs live under
+/// `[challenges.<name>]` and `batch` ones under `[batch]`, e.g.
+///
+/// ```toml
+/// [batch]
+/// retry_budget = 10
+///
+/// [challenges.mini_miner]
+/// threads = 4
+/// ```
+#[derive(Debug, Default, Deserialize)]
+pub struct Config {
+    #[serde(default)]
+    pub batch: BatchConfig,
+    #[serde(default)]
+    challenges: HashMap<String, toml::Value>,
+}
+
+/// Settings a challenge reads from its `[challenges.<name>]` section. Keys
+/// left out keep their defaults.
+pub trait ChallengeConfig: DeserializeOwned + Default {
+    const CHALLENGE: &'static str;
+}
+
+impl Config {
+    /// Loads `HA_CONFIG`, or `hackattic.toml` if present.
+    pub fn load() -> Result<Self> {
+        match std::env::var("HA_CONFIG") {
+            Ok(path) => Self::from_file(Path::new(&path)),
+            Err(_) if Path::new(DEFAULT_CONFIG_PATH).exists() => {
+                Self::from_file(Path::new(DEFAULT_CONFIG_PATH))
+            }
+            Err(_) => Ok(Config::default()),
+        }
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("unable to read config {}", path.display()))?;
+        Self::parse(&contents).with_context(|| format!("invalid config {}", path.display()))
+    }
+
+    pub fn parse(contents: &str) -> Result<Self> {
+        Ok(toml::from_str(contents)?)
+    }
+
+    pub fn challenge<C: ChallengeConfig>(&self) -> Result<C> {
+        match self.challenges.get(C::CHALLENGE) {
+            Some(section) => section
+                .clone()
+                .try_into()
+                .with_context(|| format!("invalid [challenges.{}] section", C::CHALLENGE)),
+            None => Ok(C::default()),
+        }
+    }
+}
@@ -0,0 +1,301 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{IsTerminal, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use indicatif::{ProgressBar, ProgressStyle};
+use reqwest::{
+    header::{CONTENT_RANGE, RANGE},
+    Client, Response, StatusCode,
+};
+use tracing::{debug, info, warn};
+
+use crate::download_cache::DownloadCache;
+
+/// Downloads `url` into memory, showing a progress bar when stderr is a
+/// terminal so CI logs aren't flooded. Goes through the `HA_CACHE_DIR` cache
+/// when there is one.
+pub async fn download_bytes(client: &Client, url: &str) -> Result<Vec<u8>> {
+    let progress = std::io::stderr().is_terminal().then(ProgressBar::no_length);
+    download_cached(client, url, DownloadCache::from_env().as_ref(), progress).await
+}
+
+async fn download_cached(
+    client: &Client,
+    url: &str,
+    cache: Option<&DownloadCache>,
+    progress: Option<ProgressBar>,
+) -> Result<Vec<u8>> {
+    let Some(cache) = cache else {
+        return download_with_progress(client, url, progress).await;
+    };
+    if let Some(bytes) = cache.get(url) {
+        debug!("{url} found in the download cache");
+        return Ok(bytes);
+    }
+    let bytes = download_with_progress(client, url, progress).await?;
+    // a cache that can't be written to only costs the next run a download
+    if let Err(e) = cache.put(url, &bytes) {
+        warn!("unable to cache {url}: {e:#}");
+    }
+    Ok(bytes)
+}
+
+async fn download_with_progress(
+    client: &Client,
+    url: &str,
+    progress: Option<ProgressBar>,
+) -> Result<Vec<u8>> {
+    let mut resp = client
+        .get(url)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .with_context(|| format!("failed to download {url}"))?;
+
+    let total = resp.content_length();
+    debug!("downloading {url}, {total:?} bytes");
+    start_progress(progress.as_ref(), 0, total);
+
+    // Content-Length is the server's word, only so much of it is trusted
+    let mut bytes = Vec::with_capacity(total.unwrap_or(0).min(MAX_PREALLOCATION) as usize);
+    while let Some(chunk) = resp.chunk().await? {
+        bytes.extend_from_slice(&chunk);
+        if let Some(progress) = &progress {
+            progress.inc(chunk.len() as u64);
+        }
+    }
+
+    if let Some(progress) = progress {
+        progress.finish_and_clear();
+    }
+
+    Ok(bytes)
+}
+
+/// The most `download_bytes` reserves up front, however long the server says
+/// the body is.
+const MAX_PREALLOCATION: u64 = 64 << 20;
+
+fn start_progress(progress: Option<&ProgressBar>, done: u64, remaining: Option<u64>) {
+    if let (Some(progress), Some(remaining)) = (progress, remaining) {
+        progress.set_length(done + remaining);
+        progress.set_position(done);
+        progress.set_style(
+            ProgressStyle::with_template("{bar:40} {bytes}/{total_bytes} ({eta})")
+                .expect("valid progress template"),
+        );
+    }
+}
+
+/// Where `download_to_file` keeps what it has of `dest` so far.
+pub fn partial_path(dest: &Path) -> PathBuf {
+    let mut name = dest.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    dest.with_file_name(name)
+}
+
+/// Downloads `url` to `dest` for artifacts too large to refetch on every
+/// hiccup. Bytes land in `partial_path(dest)` first, and a later call picks
+/// up where an interrupted one stopped if the server honours ranges, or
+/// starts over if it doesn't.
+pub async fn download_to_file(client: &Client, url: &str, dest: &Path) -> Result<()> {
+    let progress = std::io::stderr().is_terminal().then(ProgressBar::no_length);
+    download_resumable(client, url, dest, progress).await
+}
+
+async fn download_resumable(
+    client: &Client,
+    url: &str,
+    dest: &Path,
+    progress: Option<ProgressBar>,
+) -> Result<()> {
+    let part = partial_path(dest);
+    let mut have = fs::metadata(&part).map_or(0, |m| m.len());
+
+    let mut resp = request_from(client, url, have).await?;
+    if resp.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+        info!(
+            "{} doesn't fit {url} any more, downloading it again",
+            part.display()
+        );
+        have = 0;
+        resp = request_from(client, url, have).await?;
+    }
+    // appending from anywhere else than where the part ends would corrupt it
+    if have > 0 && resp.status() == StatusCode::PARTIAL_CONTENT && range_start(&resp) != Some(have)
+    {
+        info!("{url} was not resumed from byte {have}, downloading it again");
+        have = 0;
+        resp = request_from(client, url, have).await?;
+    }
+
+    let mut file = if have > 0 && resp.status() == StatusCode::PARTIAL_CONTENT {
+        info!("resuming {url} from byte {have}");
+        OpenOptions::new().append(true).open(&part)
+    } else {
+        if have > 0 {
+            info!("{url} can't be resumed, downloading it again");
+            have = 0;
+        }
+        File::create(&part)
+    }
+    .with_context(|| format!("unable to open {}", part.display()))?;
+
+    let remaining = resp.content_length();
+    debug!("downloading {url}, {remaining:?} bytes after the first {have}");
+    start_progress(progress.as_ref(), have, remaining);
+
+    while let Some(chunk) = resp
+        .chunk()
+        .await
+        .with_context(|| format!("download of {url} interrupted, rerun to resume"))?
+    {
+        file.write_all(&chunk)
+            .with_context(|| format!("unable to write {}", part.display()))?;
+        if let Some(progress) = &progress {
+            progress.inc(chunk.len() as u64);
+        }
+    }
+
+    if let Some(progress) = progress {
+        progress.finish_and_clear();
+    }
+
+    fs::rename(&part, dest).with_context(|| format!("unable to move {}", part.display()))
+}
+
+/// Where a 206's `Content-Range: bytes start-end/total` says its body starts.
+fn range_start(resp: &Response) -> Option<u64> {
+    let range = resp.headers().get(CONTENT_RANGE)?.to_str().ok()?;
+    let (start, _) = range.strip_prefix("bytes ")?.split_once('-')?;
+    start.trim().parse().ok()
+}
+
+async fn request_from(client: &Client, url: &str, offset: u64) -> Result<Response> {
+    let mut request = client.get(url);
+    if offset > 0 {
+        request = request.header(RANGE, format!("bytes={offset}-"));
+    }
+    let resp = request
+        .send()
+        .await
+        .with_context(|| format!("failed to download {url}"))?;
+    if resp.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+        return Ok(resp);
+    }
+    resp.error_for_status()
+        .with_context(|| format!("failed to download {url}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use wiremock::{
+        matchers::{header, header_exists, path},
+        Mock, MockServer, Request, ResponseTemplate,
+    };
+
+    use super::{download_cached, download_resumable, download_with_progress, partial_path};
+    use crate::download_cache::DownloadCache;
+
+    #[tokio::test]
+    async fn test_download_without_progress() {
+        let server = MockServer::start().await;
+        let body: Vec<u8> = (0..=255).cycle().take(100_000).collect();
+        Mock::given(path("/file.zip"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(body.clone()))
+            .mount(&server)
+            .await;
+
+        let url = format!("{}/file.zip", server.uri());
+        let bytes = download_with_progress(&reqwest::Client::new(), &url, None)
+            .await
+            .unwrap();
+        assert_eq!(bytes.len(), 100_000);
+        assert_eq!(bytes, body);
+    }
+
+    #[tokio::test]
+    async fn test_second_download_hits_the_cache() {
+        let server = MockServer::start().await;
+        Mock::given(path("/qr.png"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"png".to_vec()))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let dir = std::env::temp_dir().join(format!("cached-download-{}", std::process::id()));
+        let cache = DownloadCache::new(&dir, 1 << 20);
+        let client = reqwest::Client::new();
+        let url = format!("{}/qr.png", server.uri());
+        for _ in 0..2 {
+            let bytes = download_cached(&client, &url, Some(&cache), None)
+                .await
+                .unwrap();
+            assert_eq!(bytes, b"png");
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_download_resumes() {
+        let server = MockServer::start().await;
+        let body: Vec<u8> = (0..=255).cycle().take(100_000).collect();
+        Mock::given(path("/dump.rdb"))
+            .and(header("range", "bytes=40000-"))
+            .respond_with(
+                ResponseTemplate::new(206)
+                    .insert_header("content-range", "bytes 40000-99999/100000")
+                    .set_body_bytes(&body[40_000..]),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(path("/no-ranges.rdb"))
+            .and(header_exists("range"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(body.clone()))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        // a partial answer from the wrong offset, then the whole file
+        Mock::given(path("/wrong-range.rdb"))
+            .and(header_exists("range"))
+            .respond_with(
+                ResponseTemplate::new(206)
+                    .insert_header("content-range", "bytes 0-99999/100000")
+                    .set_body_bytes(body.clone()),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(path("/wrong-range.rdb"))
+            .and(|request: &Request| !request.headers.contains_key(&"range".into()))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(body.clone()))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let dir = std::env::temp_dir().join(format!("download-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let client = reqwest::Client::new();
+        for name in ["dump.rdb", "no-ranges.rdb", "wrong-range.rdb"] {
+            let dest = dir.join(name);
+            // what an earlier download got before the connection dropped
+            fs::write(partial_path(&dest), &body[..40_000]).unwrap();
+
+            let url = format!("{}/{name}", server.uri());
+            download_resumable(&client, &url, &dest, None)
+                .await
+                .unwrap();
+
+            assert_eq!(fs::read(&dest).unwrap(), body, "{name}");
+            assert!(!partial_path(&dest).exists());
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
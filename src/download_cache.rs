@@ -0,0 +1,162 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use tracing::{debug, warn};
+
+use crate::hackattic_context::parse_flag;
+
+/// Where downloaded artifacts are kept between runs. Unset, nothing is cached.
+pub const CACHE_DIR_ENV: &str = "HA_CACHE_DIR";
+/// Skips the cache for one run without having to unset `HA_CACHE_DIR`.
+pub const NO_CACHE_ENV: &str = "HA_NO_CACHE";
+/// How many bytes the cache may hold before the least recently used
+/// artifacts are evicted.
+pub const CACHE_MAX_BYTES_ENV: &str = "HA_CACHE_MAX_BYTES";
+
+const DEFAULT_MAX_BYTES: u64 = 1 << 30;
+
+/// Downloaded artifacts on disk, one file per URL named after its SHA-256,
+/// so reruns during development don't fetch the same image or zip again.
+/// A file's mtime is when it was last used, which is what eviction goes by.
+#[derive(Debug, Clone)]
+pub struct DownloadCache {
+    dir: PathBuf,
+    max_bytes: u64,
+}
+
+impl DownloadCache {
+    pub fn new(dir: impl Into<PathBuf>, max_bytes: u64) -> Self {
+        DownloadCache {
+            dir: dir.into(),
+            max_bytes,
+        }
+    }
+
+    pub fn from_env() -> Option<Self> {
+        Self::from_vars(std::env::vars())
+    }
+
+    fn from_vars(vars: impl Iterator<Item = (String, String)>) -> Option<Self> {
+        let vars: HashMap<_, _> = vars.collect();
+        if vars
+            .get(NO_CACHE_ENV)
+            .is_some_and(|value| parse_flag(NO_CACHE_ENV, value))
+        {
+            return None;
+        }
+        let dir = vars.get(CACHE_DIR_ENV).filter(|dir| !dir.is_empty())?;
+        let max_bytes = match vars.get(CACHE_MAX_BYTES_ENV).map(|max| max.parse()) {
+            Some(Ok(max)) => max,
+            Some(Err(_)) => {
+                warn!("{CACHE_MAX_BYTES_ENV} is not a number of bytes, using {DEFAULT_MAX_BYTES}");
+                DEFAULT_MAX_BYTES
+            }
+            None => DEFAULT_MAX_BYTES,
+        };
+        Some(Self::new(dir, max_bytes))
+    }
+
+    fn path(&self, url: &str) -> PathBuf {
+        self.dir
+            .join(base16::encode_lower(&Sha256::digest(url.as_bytes())))
+    }
+
+    /// The artifact downloaded from `url` earlier, marked as just used.
+    pub fn get(&self, url: &str) -> Option<Vec<u8>> {
+        let path = self.path(url);
+        let bytes = fs::read(&path).ok()?;
+        if let Err(e) = touch(&path) {
+            debug!("unable to mark {} as used: {e}", path.display());
+        }
+        Some(bytes)
+    }
+
+    /// Keeps `bytes` for `url`, then evicts the least recently used other
+    /// artifacts until the cache fits in its maximum size again.
+    pub fn put(&self, url: &str, bytes: &[u8]) -> Result<()> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("unable to create {}", self.dir.display()))?;
+        let path = self.path(url);
+        // written aside and renamed, so a crash never leaves half an artifact
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, bytes).with_context(|| format!("unable to write {}", tmp.display()))?;
+        fs::rename(&tmp, &path).with_context(|| format!("unable to move {}", tmp.display()))?;
+        self.evict(&path)
+    }
+
+    fn evict(&self, keep: &Path) -> Result<()> {
+        let mut entries = Vec::new();
+        let mut total = 0;
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if !metadata.is_file() {
+                continue;
+            }
+            total += metadata.len();
+            if entry.path() != keep {
+                entries.push((metadata.modified()?, metadata.len(), entry.path()));
+            }
+        }
+
+        entries.sort();
+        for (_, len, path) in entries {
+            if total <= self.max_bytes {
+                break;
+            }
+            debug!("evicting {} from the download cache", path.display());
+            fs::remove_file(&path)
+                .with_context(|| format!("unable to evict {}", path.display()))?;
+            total -= len;
+        }
+        Ok(())
+    }
+}
+
+fn touch(path: &Path) -> std::io::Result<()> {
+    File::options()
+        .write(true)
+        .open(path)?
+        .set_modified(SystemTime::now())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{DownloadCache, CACHE_DIR_ENV, NO_CACHE_ENV};
+
+    #[test]
+    fn test_least_recently_used_is_evicted() {
+        let dir = std::env::temp_dir().join(format!("download-cache-{}", std::process::id()));
+        let cache = DownloadCache::new(&dir, 150);
+
+        cache.put("https://example.com/a.zip", &[1; 100]).unwrap();
+        assert_eq!(cache.get("https://example.com/a.zip"), Some(vec![1; 100]));
+        cache.put("https://example.com/b.png", &[2; 100]).unwrap();
+
+        assert_eq!(cache.get("https://example.com/a.zip"), None);
+        assert_eq!(cache.get("https://example.com/b.png"), Some(vec![2; 100]));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_no_cache_overrides_cache_dir() {
+        let vars = |no_cache: &str| {
+            [
+                (CACHE_DIR_ENV.to_string(), "/tmp/ha-cache".to_string()),
+                (NO_CACHE_ENV.to_string(), no_cache.to_string()),
+            ]
+            .into_iter()
+        };
+        assert!(DownloadCache::from_vars(vars("0")).is_some());
+        assert!(DownloadCache::from_vars(vars("1")).is_none());
+        assert!(DownloadCache::from_vars(std::iter::empty()).is_none());
+    }
+}
@@ -0,0 +1,120 @@
+use std::fmt;
+
+use anyhow::{Context, Result};
+use base64::{
+    alphabet,
+    engine::{general_purpose, DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig},
+    Engine,
+};
+
+/// Standard alphabet, with or without padding.
+const LENIENT_BASE64: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// Textual forms a digest or other byte string can be shown in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rendering {
+    LowerHex,
+    UpperHex,
+    /// Standard alphabet, padded.
+    Base64,
+    /// Url-safe alphabet, padded.
+    UrlSafeBase64,
+}
+
+impl Rendering {
+    pub fn display(self, bytes: &[u8]) -> DigestDisplay<'_> {
+        DigestDisplay {
+            bytes,
+            rendering: self,
+        }
+    }
+
+    pub fn render(self, bytes: &[u8]) -> String {
+        self.display(bytes).to_string()
+    }
+}
+
+/// Formats bytes in a `Rendering` without building the string up front.
+#[derive(Debug, Clone, Copy)]
+pub struct DigestDisplay<'a> {
+    bytes: &'a [u8],
+    rendering: Rendering,
+}
+
+impl fmt::Display for DigestDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.rendering {
+            Rendering::LowerHex => self.bytes.iter().try_for_each(|b| write!(f, "{b:02x}")),
+            Rendering::UpperHex => self.bytes.iter().try_for_each(|b| write!(f, "{b:02X}")),
+            Rendering::Base64 => f.write_str(&general_purpose::STANDARD.encode(self.bytes)),
+            Rendering::UrlSafeBase64 => f.write_str(&general_purpose::URL_SAFE.encode(self.bytes)),
+        }
+    }
+}
+
+/// Lowercase hex, as the challenges expect.
+pub fn hex_encode(bytes: &[u8]) -> String {
+    Rendering::LowerHex.render(bytes)
+}
+
+/// Accepts upper, lower and mixed case hex.
+pub fn hex_decode(hex: &str) -> Result<Vec<u8>> {
+    base16::decode(hex).with_context(|| format!("invalid hex {hex:?}"))
+}
+
+/// Accepts standard or url-safe base64, padded or not, ignoring any
+/// whitespace or line breaks in between.
+pub fn base64_decode(encoded: &str) -> Result<Vec<u8>> {
+    let normalized: String = encoded
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| match c {
+            '-' => '+',
+            '_' => '/',
+            c => c,
+        })
+        .collect();
+    LENIENT_BASE64
+        .decode(&normalized)
+        .with_context(|| format!("invalid base64 ({} characters)", normalized.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{base64_decode, hex_decode, hex_encode, Rendering};
+
+    #[test]
+    fn test_hex_round_trip() {
+        let bytes = [0x00, 0x0f, 0xa5, 0xff];
+        assert_eq!(hex_encode(&bytes), "000fa5ff");
+        assert_eq!(hex_decode(&hex_encode(&bytes)).unwrap(), bytes);
+        assert_eq!(hex_decode("000FA5fF").unwrap(), bytes);
+        assert!(hex_decode("abc").is_err());
+        assert!(hex_decode("zz").is_err());
+    }
+
+    #[test]
+    fn test_base64_is_lenient() {
+        let bytes = [0xfb, 0xff, 0xbf, 0x00];
+        assert_eq!(base64_decode("+/+/AA==").unwrap(), bytes);
+        assert_eq!(base64_decode("-_-_AA").unwrap(), bytes);
+        assert_eq!(base64_decode(" +/+/\nAA==\r\n").unwrap(), bytes);
+        assert!(base64_decode("not base64!").is_err());
+    }
+
+    #[test]
+    fn test_renderings() {
+        let bytes = [0xfb, 0xff, 0xbf, 0x00, 0x7e];
+        assert_eq!(Rendering::LowerHex.render(&bytes), "fbffbf007e");
+        assert_eq!(Rendering::UpperHex.render(&bytes), "FBFFBF007E");
+        assert_eq!(Rendering::Base64.render(&bytes), "+/+/AH4=");
+        assert_eq!(Rendering::UrlSafeBase64.render(&bytes), "-_-_AH4=");
+        assert_eq!(
+            format!("sha256 {}", Rendering::LowerHex.display(&bytes[..2])),
+            "sha256 fbff"
+        );
+    }
+}
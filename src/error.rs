@@ -0,0 +1,54 @@
+use std::time::Duration;
+
+use thiserror::Error;
+
+use crate::Phase;
+
+#[derive(Error, Debug)]
+pub enum HackatticError {
+    #[error("not found: {0}")]
+    NotFound(String),
+    #[error("authentication failed: {0}")]
+    AuthFailed(String),
+    #[error("answer rejected: {0}")]
+    Rejected(String),
+    #[error("solver failed: {0:#}")]
+    SolverFailed(anyhow::Error),
+    #[error("cancelled")]
+    Cancelled,
+    #[error("{0} phase took longer than its {1:?} deadline")]
+    TimedOut(Phase, Duration),
+    #[error("network error: {0}")]
+    Network(#[from] reqwest::Error),
+}
+
+// solvers return `anyhow::Result`, so a `HackatticError` raised inside one
+// arrives wrapped; unwrap it instead of burying it under `SolverFailed`
+impl From<anyhow::Error> for HackatticError {
+    fn from(err: anyhow::Error) -> Self {
+        match err.downcast::<HackatticError>() {
+            Ok(err) => err,
+            Err(err) => HackatticError::SolverFailed(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HackatticError;
+
+    #[test]
+    fn test_from_anyhow_keeps_variant() {
+        let err = anyhow::Error::from(HackatticError::NotFound("nonce".into()));
+        assert!(matches!(
+            HackatticError::from(err),
+            HackatticError::NotFound(_)
+        ));
+
+        let err = anyhow::anyhow!("boom");
+        assert!(matches!(
+            HackatticError::from(err),
+            HackatticError::SolverFailed(_)
+        ));
+    }
+}
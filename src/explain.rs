@@ -0,0 +1,81 @@
+use std::fmt::Write;
+
+use anyhow::Result;
+
+use crate::{
+    backup_restore::BackupRestore, help_me_unpack::HelpMeUnpack, mini_miner::MiniMiner,
+    password_hashing::PasswordHashing, sample::sample, tales_of_ssl::TalesOfSsl, Hackattic,
+};
+
+/// What the named challenge asks for, the shapes of its problem and answer,
+/// and what has to be set up before solving it.
+pub fn explain(name: &str) -> Result<String> {
+    match name {
+        HelpMeUnpack::NAME => describe::<HelpMeUnpack>(),
+        MiniMiner::NAME => describe::<MiniMiner>(),
+        PasswordHashing::NAME => describe::<PasswordHashing>(),
+        TalesOfSsl::NAME => describe::<TalesOfSsl>(),
+        BackupRestore::NAME => describe::<BackupRestore>(),
+        _ => anyhow::bail!("no solver for {name}"),
+    }
+}
+
+fn describe<T: Hackattic>() -> Result<String> {
+    let about = T::ABOUT;
+    let mut out = format!("{}: {}\n", T::NAME, about.summary);
+
+    writeln!(out, "\nproblem: {}", about.problem)?;
+    // the built-in sample, where there is one, is a real problem of that shape
+    if let Ok(sample) = sample(T::NAME) {
+        writeln!(
+            out,
+            "e.g. {}",
+            serde_json::to_string_pretty(&sample.problem)?
+        )?;
+    }
+
+    match T::answer_schema() {
+        Some(schema) => writeln!(
+            out,
+            "\nanswer schema: {}",
+            serde_json::to_string_pretty(&schema)?
+        )?,
+        None => writeln!(out, "\nanswer schema: none")?,
+    }
+
+    match about.prerequisites {
+        [] => writeln!(out, "\nprerequisites: none")?,
+        prerequisites => {
+            writeln!(out, "\nprerequisites:")?;
+            for prerequisite in prerequisites {
+                writeln!(out, "  - {prerequisite}")?;
+            }
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::explain;
+
+    #[test]
+    fn test_explain_lists_prerequisites() {
+        let backup_restore = explain("backup_restore").unwrap();
+        assert!(backup_restore.contains("Postgres"), "{backup_restore}");
+        assert!(
+            backup_restore.contains("\"alive_ssns\""),
+            "{backup_restore}"
+        );
+
+        let help_me_unpack = explain("help_me_unpack").unwrap();
+        assert!(
+            help_me_unpack.contains("prerequisites: none"),
+            "{help_me_unpack}"
+        );
+        assert!(help_me_unpack.contains("e.g. {"), "{help_me_unpack}");
+
+        let err = explain("unknown").unwrap_err();
+        assert_eq!(err.to_string(), "no solver for unknown");
+    }
+}
@@ -1,11 +1,21 @@
 use anyhow::{anyhow, Result};
 use std::sync::OnceLock;
+use tracing::warn;
+
+use crate::{
+    config::{ChallengeConfig, Config},
+    keychain,
+};
 
 static INSTANCE: OnceLock<HackatticContext> = OnceLock::new();
 
+pub const DEFAULT_BASE_URL: &str = "https://hackattic.com";
+
 pub struct HackatticContext {
     pub access_token: String,
     pub playground: bool,
+    pub base_url: String,
+    pub config: Config,
 }
 
 impl HackatticContext {
@@ -13,23 +23,126 @@ impl HackatticContext {
         INSTANCE.get().expect("Context not initialized")
     }
 
+    /// The challenge's `[challenges.<name>]` settings from the config file.
+    pub fn challenge_config<C: ChallengeConfig>(&self) -> Result<C> {
+        self.config.challenge()
+    }
+
+    /// For solving piped problems, where nothing is fetched or submitted.
+    pub fn offline() -> Result<HackatticContext> {
+        Ok(HackatticContext {
+            access_token: String::new(),
+            playground: false,
+            base_url: DEFAULT_BASE_URL.to_string(),
+            config: Config::load()?,
+        })
+    }
+
     pub fn init() -> Result<()> {
-        let access_token = std::env::var("HA_ACCESS_TOKEN")?;
+        let access_token = access_token()?;
         let playground = std::env::var("HA_PLAYGROUND")
             .ok()
-            .into_iter()
-            .flat_map(|s| s.parse::<bool>())
-            .take(1)
-            .next()
-            .unwrap_or(false);
+            .is_some_and(|value| parse_flag("HA_PLAYGROUND", &value));
+        let base_url =
+            std::env::var("HA_BASE_URL").unwrap_or_else(|_| DEFAULT_BASE_URL.to_string());
+        let config = Config::load()?;
 
         INSTANCE
             .set(HackatticContext {
                 access_token,
                 playground,
+                base_url,
+                config,
             })
             .map_err(|_| anyhow!("failed to init context"))?;
 
         Ok(())
     }
 }
+
+/// A boolean env var, any of `1/0/true/false/yes/no/on/off` in any case.
+/// Anything else is false, with a warning rather than silently.
+pub(crate) fn parse_flag(var: &str, value: &str) -> bool {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => true,
+        "0" | "false" | "no" | "off" | "" => false,
+        _ => {
+            warn!("{var}={value:?} is not a boolean, treating it as false");
+            false
+        }
+    }
+}
+
+/// From the keychain if `HA_USE_KEYRING=1`, falling back to `HA_ACCESS_TOKEN`
+/// when the keychain can't be read.
+fn access_token() -> Result<String> {
+    if std::env::var(keychain::USE_KEYRING_ENV).is_ok_and(|v| v == "1") {
+        match keychain::load_token() {
+            Ok(token) => return Ok(token),
+            Err(e) => warn!("falling back to HA_ACCESS_TOKEN: {e:#}"),
+        }
+    }
+    Ok(std::env::var("HA_ACCESS_TOKEN")?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_flag;
+    use crate::{
+        config::Config,
+        mini_miner::MiniMinerConfig,
+        test_util::{self, capture_logs},
+    };
+
+    #[test]
+    fn test_flag_spellings() {
+        for on in ["1", "true", "TRUE", "yes", "Yes", "on", "ON"] {
+            assert!(parse_flag("HA_PLAYGROUND", on), "{on}");
+        }
+        for off in ["0", "false", "False", "no", "NO", "off", "Off", ""] {
+            assert!(!parse_flag("HA_PLAYGROUND", off), "{off}");
+        }
+
+        let (parsed, logs) = capture_logs(|| parse_flag("HA_PLAYGROUND", "maybe"));
+        assert!(!parsed);
+        assert!(
+            logs.contains(r#"HA_PLAYGROUND="maybe" is not a boolean"#),
+            "{logs}"
+        );
+    }
+
+    #[test]
+    fn test_challenge_config_section() {
+        let config = Config::parse(
+            r#"
+            [challenges.mini_miner]
+            threads = 2
+
+            [challenges.tales_of_ssl]
+            unrelated = "ignored"
+            "#,
+        )
+        .unwrap();
+        let context = test_util::context("", config);
+
+        let mini_miner = context.challenge_config::<MiniMinerConfig>().unwrap();
+        assert_eq!(mini_miner.threads, Some(2));
+
+        let unset = Config::default().challenge::<MiniMinerConfig>().unwrap();
+        assert_eq!(unset.threads, None);
+        assert_eq!(unset.scaling, None);
+
+        let scaling = Config::parse("[challenges.mini_miner.scaling]\nstart_threads = 2").unwrap();
+        let policy = scaling
+            .challenge::<MiniMinerConfig>()
+            .unwrap()
+            .scaling
+            .unwrap();
+        assert_eq!(policy.start_threads, 2);
+        assert_eq!(policy.window_secs, 2.0);
+
+        let invalid = Config::parse("[challenges.mini_miner]\nthreads = \"many\"").unwrap();
+        let err = invalid.challenge::<MiniMinerConfig>().unwrap_err();
+        assert!(err.to_string().contains("[challenges.mini_miner]"), "{err}");
+    }
+}
@@ -1,14 +1,15 @@
-use super::Hackattic;
+use super::{About, Hackattic};
 use anyhow::{Context, Result};
 use base64::{engine::general_purpose, Engine as _};
 use serde::{Deserialize, Serialize};
 
+/// `{"bytes": "<base64>"}`
 #[derive(Deserialize, Debug)]
 pub struct HelpMeUnpackProblem {
     bytes: String,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
 #[serde(rename = "")]
 pub struct HelpMeUnpackAnswer {
     int: i32,
@@ -21,6 +22,11 @@ pub struct HelpMeUnpackAnswer {
 
 pub struct HelpMeUnpack;
 
+/// int, uint, short, 2 bytes of padding, float, double, big endian double
+const PAYLOAD_LEN: usize = 4 + 4 + 2 + 2 + 4 + 8 + 8;
+/// base64 of `PAYLOAD_LEN` bytes, checked before decoding allocates
+const MAX_ENCODED_LEN: usize = PAYLOAD_LEN.div_ceil(3) * 4;
+
 fn take<const N: usize>(it: &mut impl Iterator<Item = u8>) -> Result<[u8; N]> {
     let mut v = [0u8; N];
     for elem in v.iter_mut() {
@@ -29,13 +35,60 @@ fn take<const N: usize>(it: &mut impl Iterator<Item = u8>) -> Result<[u8; N]> {
     Ok(v)
 }
 
+/// The inverse of `solve`: the payload that unpacks to `answer`, padding
+/// zeroed. For round-trip testing the layout, hackattic has no packing
+/// challenge to submit it to.
+pub fn pack(answer: &HelpMeUnpackAnswer) -> Vec<u8> {
+    let mut payload = Vec::with_capacity(PAYLOAD_LEN);
+    payload.extend(answer.int.to_le_bytes());
+    payload.extend(answer.uint.to_le_bytes());
+    payload.extend(answer.short.to_le_bytes());
+    payload.extend([0; 2]);
+    payload.extend(answer.float.to_le_bytes());
+    payload.extend(answer.double.to_le_bytes());
+    payload.extend(answer.big_endian_double.to_be_bytes());
+    payload
+}
+
 impl Hackattic for HelpMeUnpack {
     const NAME: &'static str = "help_me_unpack";
+    const ABOUT: About = About {
+        summary:
+            "Decode a base64 blob of packed little endian numbers, plus one big endian double.",
+        problem: r#"{"bytes": "<base64>"}"#,
+        prerequisites: &[],
+    };
     type Problem = HelpMeUnpackProblem;
     type Answer = HelpMeUnpackAnswer;
 
+    fn answer_schema() -> Option<serde_json::Value> {
+        Some(serde_json::json!({
+            "type": "object",
+            "required": ["int", "uint", "short", "float", "double", "big_endian_double"],
+            "properties": {
+                "int": {"type": "integer"},
+                "uint": {"type": "integer", "minimum": 0},
+                "short": {"type": "integer"},
+                "float": {"type": "number"},
+                "double": {"type": "number"},
+                "big_endian_double": {"type": "number"}
+            }
+        }))
+    }
+
     async fn solve(problem: Self::Problem) -> Result<Self::Answer> {
+        if problem.bytes.len() > MAX_ENCODED_LEN {
+            anyhow::bail!(
+                "payload is {} base64 characters, expected at most {}",
+                problem.bytes.len(),
+                MAX_ENCODED_LEN
+            );
+        }
+
         let eng = general_purpose::STANDARD.decode(problem.bytes)?;
+        if eng.len() != PAYLOAD_LEN {
+            anyhow::bail!("payload is {} bytes, expected {}", eng.len(), PAYLOAD_LEN);
+        }
 
         let mut iter = eng.iter().copied();
         let int = i32::from_le_bytes(take::<4>(&mut iter)?);
@@ -56,3 +109,120 @@ impl Hackattic for HelpMeUnpack {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use base64::{engine::general_purpose, Engine as _};
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::{
+        pack, HelpMeUnpack, HelpMeUnpackAnswer, HelpMeUnpackProblem, MAX_ENCODED_LEN, PAYLOAD_LEN,
+    };
+    use crate::{Hackattic, HackatticError};
+
+    async fn solve_bytes(bytes: &[u8]) -> anyhow::Result<()> {
+        let problem = HelpMeUnpackProblem {
+            bytes: general_purpose::STANDARD.encode(bytes),
+        };
+        HelpMeUnpack::solve(problem).await.map(|_| ())
+    }
+
+    #[tokio::test]
+    async fn test_payload_size_is_checked() {
+        solve_bytes(&[0; PAYLOAD_LEN]).await.unwrap();
+
+        let err = solve_bytes(&[0; PAYLOAD_LEN - 2]).await.unwrap_err();
+        assert_eq!(err.to_string(), "payload is 30 bytes, expected 32");
+
+        let err = solve_bytes(&[0; PAYLOAD_LEN + 1]).await.unwrap_err();
+        assert_eq!(err.to_string(), "payload is 33 bytes, expected 32");
+
+        let err = solve_bytes(&[0; 4096]).await.unwrap_err();
+        assert!(err.to_string().contains("base64 characters"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_packed_answer_unpacks_to_itself() {
+        let mut rng = StdRng::seed_from_u64(187);
+        for _ in 0..200 {
+            let answer = HelpMeUnpackAnswer {
+                int: rng.gen(),
+                uint: rng.gen(),
+                short: rng.gen(),
+                float: rng.gen_range(-1e6..1e6),
+                double: rng.gen_range(-1e12..1e12),
+                big_endian_double: rng.gen_range(-1e12..1e12),
+            };
+            let payload = pack(&answer);
+            assert_eq!(payload.len(), PAYLOAD_LEN);
+
+            let problem = HelpMeUnpackProblem {
+                bytes: general_purpose::STANDARD.encode(&payload),
+            };
+            assert_eq!(HelpMeUnpack::solve(problem).await.unwrap(), answer);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_short_payload_is_solver_failure() {
+        let problem = HelpMeUnpackProblem {
+            bytes: "AAAA".to_string(),
+        };
+
+        let err = HelpMeUnpack::solve(problem).await.unwrap_err();
+        assert!(matches!(
+            HackatticError::from(err),
+            HackatticError::SolverFailed(_)
+        ));
+    }
+
+    /// Inputs that once tripped, or could trip, the parser: every length
+    /// around the payload size, NaN and infinite floats, and strings that
+    /// aren't base64 at all.
+    const CORPUS: &[&str] = &[
+        "",
+        "=",
+        "====",
+        "AA",
+        "AAA=",
+        "not base64!",
+        "////////////////////////////////////////////",
+        "wB3+/wBe0LIu+wAAAABgQK36XG1FSpNAwFiw/NbrM8A",
+        "wB3+/wBe0LIu+wAAAABgQK36XG1FSpNAwFiw/NbrM8A==",
+        "AAAAAAAAAAAAAP//wH8AAAAAAAD4fwAAAAAAAPB/",
+        "AAAAAAAAAAAAAP//wH8AAAAAAAD4fwAAAAAAAPB/AAA=",
+        "8J+mgPCfpoDwn6aA8J+mgPCfpoDwn6aA8J+mgPCfpoA=",
+    ];
+
+    async fn solve_str(bytes: &str) -> anyhow::Result<()> {
+        let problem = HelpMeUnpackProblem {
+            bytes: bytes.to_string(),
+        };
+        HelpMeUnpack::solve(problem).await.map(|_| ())
+    }
+
+    // panics fail the test, errors are what malformed input should produce
+    #[tokio::test]
+    async fn test_arbitrary_input_never_panics() {
+        for bytes in CORPUS {
+            let _ = solve_str(bytes).await;
+        }
+
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+        for _ in 0..2000 {
+            let len = rng.gen_range(0..=PAYLOAD_LEN * 2);
+            let payload: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+            match solve_bytes(&payload).await {
+                Ok(()) => assert_eq!(len, PAYLOAD_LEN),
+                Err(err) => {
+                    assert_ne!(len, PAYLOAD_LEN, "{err}");
+                    assert!(err.to_string().starts_with("payload is "), "{err}");
+                }
+            }
+
+            let len = rng.gen_range(0..=MAX_ENCODED_LEN + 4);
+            let text: String = (0..len).map(|_| rng.gen_range(' '..='~')).collect();
+            let _ = solve_str(&text).await;
+        }
+    }
+}
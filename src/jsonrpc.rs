@@ -0,0 +1,152 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use anyhow::{Context, Result};
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use thiserror::Error;
+use tracing::debug;
+
+/// The `error` object of a JSON-RPC 2.0 response.
+#[derive(Error, Deserialize, Debug, PartialEq)]
+#[error("rpc error {code}: {message}")]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(default)]
+    pub data: Option<Value>,
+}
+
+#[derive(Deserialize, Debug)]
+struct RpcResponse {
+    id: Value,
+    result: Option<Value>,
+    error: Option<RpcError>,
+}
+
+/// A minimal JSON-RPC 2.0 client over HTTP, for the few calls the node
+/// talking challenges need.
+#[derive(Debug)]
+pub struct JsonRpcClient {
+    client: Client,
+    url: String,
+    next_id: AtomicU64,
+}
+
+impl JsonRpcClient {
+    pub fn new(client: Client, url: impl Into<String>) -> Self {
+        JsonRpcClient {
+            client,
+            url: url.into(),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Calls `method`, returning its `result`. An error object comes back as
+    /// an `RpcError`, a response for another request id as an error too.
+    pub async fn call(&self, method: &str, params: Value) -> Result<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let request = json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params});
+        debug!("rpc request {request}");
+
+        let response: RpcResponse = self
+            .client
+            .post(&self.url)
+            .json(&request)
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status())
+            .with_context(|| format!("rpc call {method} to {} failed", self.url))?
+            .json()
+            .await
+            .with_context(|| format!("rpc call {method} returned no JSON-RPC response"))?;
+
+        if response.id != json!(id) {
+            anyhow::bail!(
+                "rpc call {method} was answered for id {}, expected {id}",
+                response.id
+            );
+        }
+        match (response.result, response.error) {
+            (_, Some(error)) => Err(error.into()),
+            (Some(result), None) => Ok(result),
+            (None, None) => anyhow::bail!("rpc call {method} returned neither result nor error"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use wiremock::{
+        matchers::{body_partial_json, method},
+        Mock, MockServer, Request, ResponseTemplate,
+    };
+
+    use super::{JsonRpcClient, RpcError};
+
+    /// Echoes the request id back, as a node would.
+    fn respond(body: serde_json::Value) -> impl Fn(&Request) -> ResponseTemplate {
+        move |request: &Request| {
+            let request: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+            let mut body = body.clone();
+            body["jsonrpc"] = json!("2.0");
+            body["id"] = request["id"].clone();
+            ResponseTemplate::new(200).set_body_json(body)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_result_and_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(json!({"method": "eth_blockNumber"})))
+            .respond_with(respond(json!({"result": "0x10"})))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(json!({"method": "eth_call"})))
+            .respond_with(respond(
+                json!({"error": {"code": -32000, "message": "execution reverted"}}),
+            ))
+            .mount(&server)
+            .await;
+
+        let rpc = JsonRpcClient::new(reqwest::Client::new(), server.uri());
+        let block = rpc.call("eth_blockNumber", json!([])).await.unwrap();
+        assert_eq!(block, "0x10");
+
+        let err = rpc
+            .call("eth_call", json!([{}, "latest"]))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<RpcError>(),
+            Some(&RpcError {
+                code: -32000,
+                message: "execution reverted".to_string(),
+                data: None,
+            })
+        );
+        assert_eq!(err.to_string(), "rpc error -32000: execution reverted");
+    }
+
+    #[tokio::test]
+    async fn test_mismatched_id_is_rejected() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!({"jsonrpc": "2.0", "id": 99, "result": "0x1"})),
+            )
+            .mount(&server)
+            .await;
+
+        let rpc = JsonRpcClient::new(reqwest::Client::new(), server.uri());
+        let err = rpc.call("eth_chainId", json!([])).await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "rpc call eth_chainId was answered for id 99, expected 1"
+        );
+    }
+}
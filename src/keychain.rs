@@ -0,0 +1,134 @@
+use std::{
+    io::Write,
+    process::{Command, Output, Stdio},
+};
+
+use anyhow::{Context, Result};
+
+/// Set to `1` to read the access token from the OS keychain, see `login`.
+pub const USE_KEYRING_ENV: &str = "HA_USE_KEYRING";
+
+const SERVICE: &str = "hackattic";
+const ACCOUNT: &str = "access_token";
+
+/// Saves the access token in the OS secret store, replacing any earlier one.
+pub fn store_token(token: &str) -> Result<()> {
+    store(ACCOUNT, token)
+}
+
+pub fn load_token() -> Result<String> {
+    load(ACCOUNT)
+}
+
+// the secret store is reached through its CLI, `security` on macOS and
+// libsecret's `secret-tool` elsewhere, so no native bindings are needed. The
+// token goes over stdin either way, so it never shows up in the process list
+fn store(account: &str, token: &str) -> Result<()> {
+    if cfg!(target_os = "macos") {
+        // `security -i` runs commands read from stdin, which is the only way
+        // to pass it the password without putting it in argv
+        let mut command = Command::new("security");
+        command.arg("-i");
+        let line = format!(
+            "add-generic-password -U -s {} -a {} -w {}\n",
+            quote(SERVICE),
+            quote(account),
+            quote(token)
+        );
+        run(command, Some(&line))
+    } else {
+        let mut command = Command::new("secret-tool");
+        command.args([
+            "store",
+            "--label=hackattic access token",
+            "service",
+            SERVICE,
+            "account",
+            account,
+        ]);
+        run(command, Some(token))
+    }
+    .context("unable to store the access token in the keychain")?;
+    Ok(())
+}
+
+/// Quotes an argument for `security -i`'s command line parser.
+fn quote(arg: &str) -> String {
+    format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn load(account: &str) -> Result<String> {
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = Command::new("security");
+        command.args(["find-generic-password", "-s", SERVICE, "-a", account, "-w"]);
+        command
+    } else {
+        let mut command = Command::new("secret-tool");
+        command.args(["lookup", "service", SERVICE, "account", account]);
+        command
+    };
+    let output = command
+        .stderr(Stdio::piped())
+        .output()
+        .with_context(|| format!("unable to run {:?}", command.get_program()))?;
+    let token = String::from_utf8(check(&command, output)?.stdout)
+        .context("the keychain's access token is not UTF-8")?;
+    let token = token.trim();
+    if token.is_empty() {
+        anyhow::bail!("no access token in the keychain, store one with `login`");
+    }
+    Ok(token.to_string())
+}
+
+fn run(mut command: Command, input: Option<&str>) -> Result<()> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("unable to run {:?}", command.get_program()))?;
+    {
+        let mut stdin = child.stdin.take().context("child stdin was not captured")?;
+        if let Some(input) = input {
+            stdin.write_all(input.as_bytes())?;
+        }
+    }
+    check(&command, child.wait_with_output()?)?;
+    Ok(())
+}
+
+fn check(command: &Command, output: Output) -> Result<Output> {
+    if !output.status.success() {
+        anyhow::bail!(
+            "{:?} exited with {}: {}",
+            command.get_program(),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::quote;
+
+    #[test]
+    fn test_security_arguments_are_quoted() {
+        assert_eq!(quote("s3cr3t"), r#""s3cr3t""#);
+        assert_eq!(quote(r#"a "b" \c"#), r#""a \"b\" \\c""#);
+    }
+
+    // writes to the real keychain, so only with `--features keychain-tests`
+    #[cfg(feature = "keychain-tests")]
+    #[test]
+    fn test_stored_token_is_loaded() {
+        use super::{load, store};
+
+        store("test_token", "s3cr3t").unwrap();
+        assert_eq!(load("test_token").unwrap(), "s3cr3t");
+
+        store("test_token", "rotated").unwrap();
+        assert_eq!(load("test_token").unwrap(), "rotated");
+    }
+}
@@ -0,0 +1,243 @@
+use std::{
+    fmt::{self, Debug},
+    future::Future,
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
+
+use batch::RetryBudget;
+use cassette::Cassette;
+use hackattic_context::HackatticContext;
+use progress::Progress;
+use rand::{rngs::StdRng, SeedableRng};
+use serde::{de::DeserializeOwned, Serialize};
+
+pub mod backup_restore;
+pub mod batch;
+pub mod bruteforce;
+pub mod cassette;
+pub mod config;
+pub mod download;
+pub mod download_cache;
+pub mod encoding;
+pub mod error;
+pub mod explain;
+pub mod hackattic_context;
+pub mod help_me_unpack;
+pub mod jsonrpc;
+pub mod keychain;
+pub mod looping;
+pub mod metrics;
+pub mod mini_miner;
+pub mod notify;
+pub mod password_hashing;
+pub mod postgres_tls;
+pub mod pretty;
+pub mod progress;
+pub mod query;
+pub mod regress;
+pub mod runner;
+pub mod sample;
+pub mod secret;
+pub mod service;
+pub mod status;
+pub mod tales_of_ssl;
+#[cfg(test)]
+mod test_util;
+pub mod wait;
+
+pub use error::HackatticError;
+pub use runner::{
+    answer, build_client, diff_problems, fetch_raw_problem, load_answer, read_problem, save_answer,
+    solve, submit_answer, submit_raw_answer,
+};
+
+/// How an answer is encoded when it is posted to the solve endpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubmitFormat {
+    Json,
+    Form,
+}
+
+/// What the solve endpoint made of an answer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SolveOutcome {
+    /// The response body, as returned to the caller.
+    Accepted(String),
+    /// Why the answer was turned down.
+    Rejected(String),
+}
+
+/// The steps of a solve, timed and bounded separately so a slow solver can
+/// be told from a slow network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Fetch,
+    Solve,
+    Submit,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Phase::Fetch => "fetch",
+            Phase::Solve => "solve",
+            Phase::Submit => "submit",
+        })
+    }
+}
+
+/// How long each phase may take, unbounded if unset.
+#[derive(Debug, Default, Clone)]
+pub struct PhaseDeadlines {
+    pub fetch: Option<Duration>,
+    pub solve: Option<Duration>,
+    pub submit: Option<Duration>,
+}
+
+impl PhaseDeadlines {
+    pub fn get(&self, phase: Phase) -> Option<Duration> {
+        match phase {
+            Phase::Fetch => self.fetch,
+            Phase::Solve => self.solve,
+            Phase::Submit => self.submit,
+        }
+    }
+}
+
+/// Command line knobs that adjust how problems are solved.
+#[derive(Debug, Default, Clone)]
+pub struct SolveOptions {
+    /// Replaces mini_miner's difficulty so the flow can be tested quickly.
+    pub difficulty_override: Option<u32>,
+    /// Checks answers against their challenge's schema before submitting.
+    pub validate_schema: bool,
+    /// Caps mini_miner's nonce search, the full `i32` range if unset.
+    pub max_nonce: Option<i32>,
+    /// Top level answer fields renamed `(old, new)` before submitting, to
+    /// probe what a new challenge expects.
+    pub answer_renames: Vec<(String, String)>,
+    /// Solves again from a fresh problem this many times after a rejection.
+    pub resubmit_on_reject: u32,
+    /// Solves a fresh problem this many times when the server says the old
+    /// one expired before the answer arrived.
+    pub refetch_on_expired: u32,
+    /// Logs every outgoing request in full, access token redacted.
+    pub dump_request: bool,
+    /// Query parameters added to every request, for endpoints that take more
+    /// than the token and playground flag.
+    pub extra_params: Vec<(String, String)>,
+    /// Seeds solvers that make random choices, drawn from entropy if unset.
+    pub seed: Option<u64>,
+    /// Writes each answer here before submitting it, so a submission lost to
+    /// the network can be replayed with `resubmit`.
+    pub save_answer: Option<PathBuf>,
+    /// Fails a solve whose fetch, solve or submit phase runs too long.
+    pub deadlines: PhaseDeadlines,
+    /// Bounds resubmits and refetches across every solve sharing it.
+    pub retry_budget: Option<Arc<RetryBudget>>,
+    /// Watched and cancellable progress of long solvers.
+    pub progress: Option<Progress>,
+    /// Records every request and response, to replay the exchange offline.
+    pub cassette: Option<Cassette>,
+    /// Prints the answer's JSON to stdout once the server accepts it.
+    pub print_answer: bool,
+    /// Logs likely causes when an answer is rejected, see
+    /// `Hackattic::diagnose`.
+    pub explain_failure: bool,
+    /// Has mini_miner check and time its templated serialization against
+    /// serde's before mining.
+    pub bench_serialization: bool,
+}
+
+impl SolveOptions {
+    /// The RNG randomized solver steps should draw from. The seed is logged
+    /// so a failing run can be repeated with `--seed`.
+    pub fn rng(&self) -> StdRng {
+        let seed = self.seed.unwrap_or_else(rand::random);
+        tracing::info!("rng seed {seed}");
+        StdRng::seed_from_u64(seed)
+    }
+}
+
+/// What `explain` says about a challenge beyond its answer schema.
+#[derive(Debug, Clone, Copy)]
+pub struct About {
+    /// What the challenge asks for, in a sentence or two.
+    pub summary: &'static str,
+    /// The problem JSON's shape.
+    pub problem: &'static str,
+    /// Services and tools the solver needs besides the binary.
+    pub prerequisites: &'static [&'static str],
+}
+
+impl About {
+    pub const UNDESCRIBED: About = About {
+        summary: "",
+        problem: "",
+        prerequisites: &[],
+    };
+}
+
+pub trait Hackattic {
+    const NAME: &'static str;
+    const ABOUT: About = About::UNDESCRIBED;
+    const SUBMIT_AS: SubmitFormat = SubmitFormat::Json;
+    /// Whether `--resubmit-on-reject` may solve the challenge again. Off for
+    /// challenges that burn a lot of CPU per attempt.
+    const RESUBMITTABLE: bool = true;
+    /// Gzips JSON answers with `Content-Encoding: gzip`. Only worth it for
+    /// large answers, and only for challenges whose server is known to
+    /// accept it, so no challenge turns it on yet.
+    const COMPRESS_ANSWER: bool = false;
+    type Problem: DeserializeOwned + Debug;
+    /// Posted as whatever JSON it serializes to, so a newtype or scalar
+    /// answer goes up as a bare `"value"`. `SubmitFormat::Form` needs a
+    /// struct.
+    type Answer: Serialize + DeserializeOwned + Debug;
+
+    fn solve(problem: Self::Problem) -> impl Future<Output = anyhow::Result<Self::Answer>> + Send;
+    /// Adjusts a fetched problem for local testing. Only called for
+    /// playground runs, never when a wrong answer would count.
+    fn override_problem(_problem: &mut Self::Problem, _options: &SolveOptions) {}
+    /// Applies options that only bound how the answer is searched for, so
+    /// unlike `override_problem` it is called on every run.
+    fn apply_options(_problem: &mut Self::Problem, _options: &SolveOptions) {}
+    /// Applies the challenge's section of the config file.
+    fn configure(_problem: &mut Self::Problem, _context: &HackatticContext) -> anyhow::Result<()> {
+        Ok(())
+    }
+    /// Checks invariants of an answer that no correct one breaks, before it
+    /// is submitted or printed.
+    fn validate_answer(_answer: &Self::Answer) -> anyhow::Result<()> {
+        Ok(())
+    }
+    /// Likely causes of a rejection, found by rechecking the answer without
+    /// its problem. Only run after a rejection with
+    /// `SolveOptions::explain_failure`.
+    fn diagnose(_answer: &Self::Answer) -> Vec<String> {
+        Vec::new()
+    }
+    /// JSON schema the serialized answer must satisfy, checked when
+    /// `SolveOptions::validate_schema` is set.
+    fn answer_schema() -> Option<serde_json::Value> {
+        None
+    }
+    /// Undoes whatever `solve` left behind locally (loaded tables, spawned
+    /// services) so the next run starts from a clean slate.
+    fn clean(_context: &HackatticContext) -> impl Future<Output = anyhow::Result<()>> + Send {
+        async { Ok(()) }
+    }
+    fn problem_url(base_url: &str) -> String {
+        runner::problem_url(base_url, Self::NAME)
+    }
+    fn solve_url(base_url: &str) -> String {
+        runner::solve_url(base_url, Self::NAME)
+    }
+    /// Reads the solve endpoint's response, for challenges that don't answer
+    /// with the usual `{"rejected": ...}` or `{"error": ...}` on failure.
+    fn interpret_response(body: &str) -> anyhow::Result<SolveOutcome> {
+        Ok(runner::interpret_response(body))
+    }
+}
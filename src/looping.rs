@@ -0,0 +1,153 @@
+use std::{fs, future::Future, path::PathBuf};
+
+use anyhow::{Context, Result};
+use tracing::{info, warn};
+
+use crate::HackatticError;
+
+#[derive(Debug, Clone, Default)]
+pub struct LoopOptions {
+    pub attempts: u32,
+    pub stop_on_fail: bool,
+    /// Where the best streak is kept between runs.
+    pub streak_file: Option<PathBuf>,
+}
+
+/// Consecutive successful submissions within a run.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Streak {
+    pub current: u32,
+    pub best: u32,
+}
+
+impl Streak {
+    pub fn record(&mut self, passed: bool) {
+        if passed {
+            self.current += 1;
+            self.best = self.best.max(self.current);
+        } else {
+            self.current = 0;
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct LoopReport {
+    pub streak: Streak,
+    /// One line per attempt, as logged.
+    pub log: Vec<String>,
+}
+
+/// Runs `attempt` up to `options.attempts` times, tracking the streak of
+/// successes.
+pub async fn run_loop<F, Fut>(options: &LoopOptions, mut attempt: F) -> Result<LoopReport>
+where
+    F: FnMut(u32) -> Fut,
+    Fut: Future<Output = Result<String, HackatticError>>,
+{
+    let mut streak = Streak {
+        best: load_best(options)?,
+        ..Default::default()
+    };
+    let mut log = Vec::new();
+
+    for n in 1..=options.attempts {
+        let outcome = attempt(n).await;
+        streak.record(outcome.is_ok());
+
+        let line = match &outcome {
+            Ok(_) => format!(
+                "attempt {n}/{}: passed, streak {} (best {})",
+                options.attempts, streak.current, streak.best
+            ),
+            Err(e) => format!(
+                "attempt {n}/{}: failed: {e}, streak reset (best {})",
+                options.attempts, streak.best
+            ),
+        };
+        info!("{line}");
+        log.push(line);
+
+        if outcome.is_err() && options.stop_on_fail {
+            warn!("stopping after failed attempt {n}");
+            break;
+        }
+    }
+
+    save_best(options, streak.best)?;
+    Ok(LoopReport { streak, log })
+}
+
+fn load_best(options: &LoopOptions) -> Result<u32> {
+    match &options.streak_file {
+        Some(path) if path.exists() => fs::read_to_string(path)
+            .with_context(|| format!("unable to read {}", path.display()))?
+            .trim()
+            .parse()
+            .with_context(|| format!("{} does not hold a streak", path.display())),
+        _ => Ok(0),
+    }
+}
+
+fn save_best(options: &LoopOptions, best: u32) -> Result<()> {
+    if let Some(path) = &options.streak_file {
+        fs::write(path, best.to_string())
+            .with_context(|| format!("unable to write {}", path.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{run_loop, LoopOptions};
+    use crate::HackatticError;
+
+    fn outcome(n: u32) -> Result<String, HackatticError> {
+        if n == 3 {
+            Err(HackatticError::Rejected("wrong".to_string()))
+        } else {
+            Ok("passed".to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_streak_resets_on_failure() {
+        let options = LoopOptions {
+            attempts: 4,
+            ..Default::default()
+        };
+        let report = run_loop(&options, |n| async move { outcome(n) })
+            .await
+            .unwrap();
+
+        assert_eq!(
+            report.log,
+            [
+                "attempt 1/4: passed, streak 1 (best 1)",
+                "attempt 2/4: passed, streak 2 (best 2)",
+                "attempt 3/4: failed: answer rejected: wrong, streak reset (best 2)",
+                "attempt 4/4: passed, streak 1 (best 2)",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stop_on_fail_and_persisted_best() {
+        let streak_file = std::env::temp_dir().join(format!("streak-{}", std::process::id()));
+        std::fs::write(&streak_file, "5").unwrap();
+
+        let options = LoopOptions {
+            attempts: 4,
+            stop_on_fail: true,
+            streak_file: Some(streak_file.clone()),
+        };
+        let report = run_loop(&options, |n| async move { outcome(n) })
+            .await
+            .unwrap();
+
+        assert_eq!(report.log.len(), 3);
+        assert_eq!(report.streak.best, 5);
+        assert_eq!(std::fs::read_to_string(&streak_file).unwrap(), "5");
+        std::fs::remove_file(streak_file).unwrap();
+    }
+}
@@ -1,105 +1,653 @@
-use std::{collections::HashMap, env, fmt::Debug};
+use std::{
+    fs,
+    io::IsTerminal,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use anyhow::{Context, Result};
-use reqwest::{Client, ClientBuilder};
-use serde::{de::DeserializeOwned, Serialize};
+use clap::{Args, Parser, Subcommand};
+use reqwest::Client;
 use tracing::*;
 
-mod help_me_unpack;
-use help_me_unpack::HelpMeUnpack;
-mod mini_miner;
-use mini_miner::MiniMiner;
-mod hackattic_context;
-use hackattic_context::HackatticContext;
-mod password_hashing;
-use password_hashing::PasswordHashing;
-mod tales_of_ssl;
-use tales_of_ssl::TalesOfSsl;
-mod backup_restore;
-use backup_restore::BackupRestore;
+use hackattic::{
+    answer,
+    backup_restore::BackupRestore,
+    batch::{self, RetryBudget},
+    build_client,
+    cassette::Cassette,
+    diff_problems, explain, fetch_raw_problem,
+    hackattic_context::HackatticContext,
+    help_me_unpack::HelpMeUnpack,
+    keychain, load_answer,
+    looping::{self, LoopOptions},
+    metrics,
+    mini_miner::MiniMiner,
+    notify::{notify, Notification},
+    password_hashing::PasswordHashing,
+    pretty,
+    progress::Progress,
+    read_problem, regress, sample, solve, status, submit_answer, submit_raw_answer,
+    tales_of_ssl::TalesOfSsl,
+    Hackattic, HackatticError, PhaseDeadlines, SolveOptions,
+};
 
-trait Hackattic {
-    const NAME: &'static str;
-    type Problem: DeserializeOwned + Debug;
-    type Answer: Serialize + Debug;
+#[derive(Parser, Debug)]
+#[command(version, about = "Solutions for the hackattic.com challenges")]
+struct Cli {
+    /// Log nothing but errors, to stderr, so stdout carries only the answer
+    /// JSON and can be piped
+    #[arg(long, global = true)]
+    quiet: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Fetch a challenge's problem, solve it and submit the answer
+    Solve(Box<SolveArgs>),
+    /// Solve several challenges concurrently and summarise the results
+    Batch {
+        #[arg(required = true)]
+        names: Vec<String>,
+        /// POST a JSON summary of each challenge's result to this webhook
+        #[arg(long)]
+        notify_url: Option<String>,
+        /// Run at most this many solves at once, one per distinct challenge by default
+        #[arg(long)]
+        concurrency: Option<usize>,
+        /// Solve each challenge again up to this many times if its answer is rejected
+        #[arg(long, default_value_t = 0)]
+        resubmit_on_reject: u32,
+        /// Solve a fresh problem up to this many times if the old one expired
+        #[arg(long, default_value_t = 1)]
+        refetch_on_expired: u32,
+        /// Serve Prometheus metrics on this address, e.g. 127.0.0.1:9898
+        #[arg(long)]
+        metrics_addr: Option<SocketAddr>,
+    },
+    /// Undo the local side effects of solving the given challenges
+    Clean {
+        #[arg(required = true)]
+        names: Vec<String>,
+    },
+    /// Submit an answer saved with `solve --save-answer` without solving again
+    Resubmit {
+        name: String,
+        #[arg(long)]
+        answer_file: PathBuf,
+    },
+    /// Fetch a challenge's problem twice and show which fields changed between fetches
+    DiffProblems { name: String },
+    /// Check a solver against its built-in worked sample, offline
+    Sample { name: String },
+    /// Describe what a challenge asks for, its problem and answer shapes, and its prerequisites
+    Explain { name: String },
+    /// Store an access token read from stdin in the OS keychain, used with HA_USE_KEYRING=1
+    Login,
+    /// Show which challenges the access token has solved, told apart in playground mode
+    Status,
+    /// Run every solver against its stored fixture, offline
+    Regress {
+        #[arg(long, default_value = "fixtures")]
+        fixtures: PathBuf,
+    },
+}
 
-    async fn solve(problem: Self::Problem) -> Result<Self::Answer>;
-    fn problem_url() -> String {
-        format!("https://hackattic.com/challenges/{}/problem/", Self::NAME)
+#[derive(Args, Debug)]
+struct SolveArgs {
+    name: String,
+    /// Replace mini_miner's difficulty, honoured in playground mode only
+    #[arg(long)]
+    difficulty_override: Option<u32>,
+    /// Check the answer against the challenge's JSON schema before submitting
+    #[arg(long)]
+    validate_schema: bool,
+    /// Read the problem JSON from stdin instead of fetching it, and print the answer
+    #[arg(long)]
+    stdin: bool,
+    /// Submit the answer to a problem read with --stdin
+    #[arg(long, requires = "stdin")]
+    submit: bool,
+    /// Only fetch and print the problem JSON, as done for challenges without a solver
+    #[arg(long)]
+    problem_only_json: bool,
+    /// Print fetched problems indented and colored, with long strings cut short
+    #[arg(long)]
+    pretty: bool,
+    /// Submit the JSON in this file as the answer to a raw problem
+    #[arg(long)]
+    answer_json: Option<PathBuf>,
+    /// Solve and submit this many times in a row, tracking the streak
+    #[arg(long, default_value_t = 1)]
+    attempts: u32,
+    /// Stop looping at the first failed attempt
+    #[arg(long)]
+    stop_on_fail: bool,
+    /// Stop mini_miner's search at this nonce instead of i32::MAX
+    #[arg(long, value_parser = clap::value_parser!(i32).range(0..))]
+    max_nonce: Option<i32>,
+    /// Before mining, hash mini_miner's candidates through serde and from a
+    /// template, check they agree and log the speedup
+    #[arg(long)]
+    bench_serialization: bool,
+    /// Solve again up to this many times if the answer is rejected, for flaky
+    /// service challenges. Ignored for costly ones like mini_miner
+    #[arg(long, default_value_t = 0)]
+    resubmit_on_reject: u32,
+    /// Solve a fresh problem up to this many times if the old one expired
+    /// while being solved. Applies to every challenge
+    #[arg(long, default_value_t = 1)]
+    refetch_on_expired: u32,
+    /// Rename an answer field before submitting, as old=new. Repeatable
+    #[arg(long = "answer-rename", value_name = "OLD=NEW", value_parser = parse_rename)]
+    answer_renames: Vec<(String, String)>,
+    /// Add a query parameter to every request, as key=value. Repeatable
+    #[arg(long = "param", value_name = "KEY=VALUE", value_parser = parse_param)]
+    params: Vec<(String, String)>,
+    /// Fail if fetching the problem takes longer than this many seconds
+    #[arg(long, value_name = "SECS", value_parser = parse_secs)]
+    fetch_deadline: Option<Duration>,
+    /// Fail if solving takes longer than this many seconds
+    #[arg(long, value_name = "SECS", value_parser = parse_secs)]
+    solve_deadline: Option<Duration>,
+    /// Warn if submitting takes longer than this many seconds. The submit is
+    /// never abandoned, the server may already have the answer
+    #[arg(long, value_name = "SECS", value_parser = parse_secs)]
+    submit_deadline: Option<Duration>,
+    /// Seed randomized solver steps so a run can be reproduced
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Log every outgoing request in full, with the access token redacted
+    #[arg(long)]
+    dump_request: bool,
+    /// POST a JSON summary of the result to this webhook when the solve
+    /// finishes, or after each of --attempts
+    #[arg(long)]
+    notify_url: Option<String>,
+    /// Save the answer to this file before submitting it, see `resubmit`.
+    /// mini_miner also saves its winning block and hash beside it
+    #[arg(long)]
+    save_answer: Option<PathBuf>,
+    /// Log a histogram of request latencies and the slowest request at the end
+    #[arg(long)]
+    latency_report: bool,
+    /// Record every request and response to this cassette file, access
+    /// token redacted, for replaying the exchange in tests
+    #[arg(long, value_name = "FILE")]
+    record: Option<PathBuf>,
+    /// After a rejection, recheck the answer and log likely causes
+    #[arg(long)]
+    explain_failure: bool,
+    /// Set from the global --quiet
+    #[arg(skip)]
+    print_answer: bool,
+    /// Keep the best streak in this file across runs
+    #[arg(long)]
+    streak_file: Option<PathBuf>,
+    /// Serve Prometheus metrics on this address, e.g. 127.0.0.1:9898
+    #[arg(long)]
+    metrics_addr: Option<SocketAddr>,
+}
+
+fn parse_rename(rename: &str) -> Result<(String, String), String> {
+    match rename.split_once('=') {
+        Some((old, new)) if !old.is_empty() && !new.is_empty() => {
+            Ok((old.to_string(), new.to_string()))
+        }
+        _ => Err(format!("expected old=new, got {rename:?}")),
     }
-    fn solve_url() -> String {
-        format!("https://hackattic.com/challenges/{}/solve/", Self::NAME)
+}
+
+// unlike a rename, the value may be empty
+fn parse_param(param: &str) -> Result<(String, String), String> {
+    match param.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("expected key=value, got {param:?}")),
     }
 }
 
+fn parse_secs(secs: &str) -> Result<Duration, String> {
+    secs.parse()
+        .ok()
+        .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+        .ok_or_else(|| format!("expected a number of seconds, got {secs:?}"))
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
-    HackatticContext::init()?;
-    let subscriber = tracing_subscriber::FmtSubscriber::builder()
-        .with_max_level(Level::DEBUG)
-        .finish();
-    tracing::subscriber::set_global_default(subscriber)
-        .context("setting default tracing subscriber failed")?;
+    let cli = Cli::parse();
+
+    init_tracing(cli.quiet);
 
-    let client = ClientBuilder::new().cookie_store(true).build()?;
+    match cli.command {
+        Command::Solve(mut args) => {
+            args.print_answer = cli.quiet;
+            let latency_report = args.latency_report;
+            let record = args.record.clone();
+            let cassette = Cassette::default();
+            let result = solve_challenge(*args, record.is_some().then(|| cassette.clone())).await;
+            if latency_report {
+                let report = metrics::global().latency_report();
+                // under --quiet stdout is the answer's alone
+                match cli.quiet {
+                    true => eprintln!("{report}"),
+                    false => info!("{report}"),
+                }
+            }
+            if let Some(path) = record {
+                cassette.save(&path)?;
+                info!("recorded the exchange to {}", path.display());
+            }
+            result
+        }
+        Command::Batch {
+            names,
+            notify_url,
+            concurrency,
+            resubmit_on_reject,
+            refetch_on_expired,
+            metrics_addr,
+        } => {
+            if let Some(addr) = metrics_addr {
+                metrics::serve(addr)?;
+            }
+            let options = SolveOptions {
+                resubmit_on_reject,
+                refetch_on_expired,
+                ..Default::default()
+            };
+            solve_batch(names, notify_url, concurrency, options, cli.quiet).await
+        }
+        Command::Clean { names } => clean(names).await,
+        Command::Resubmit { name, answer_file } => resubmit_named(&name, &answer_file).await,
+        Command::DiffProblems { name } => show_problem_diff(&name).await,
+        Command::Sample { name } => {
+            sample::check_sample(&name).await?;
+            info!("{name}: sample ok");
+            Ok(())
+        }
+        Command::Explain { name } => {
+            println!("{}", explain::explain(&name)?);
+            Ok(())
+        }
+        Command::Login => login(),
+        Command::Status => show_status().await,
+        Command::Regress { fixtures } => regress::run(&fixtures).await,
+    }
+}
 
-    let args: Vec<_> = env::args().collect();
+/// Progress for a solve that the first Ctrl-C cancels, a second one exits
+/// without waiting for the solver to notice.
+fn cancel_on_ctrl_c() -> Progress {
+    let progress = Progress::new();
+    let cancel = progress.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            warn!("cancelling, press Ctrl-C again to exit now");
+            cancel.cancel();
+        }
+        if tokio::signal::ctrl_c().await.is_ok() {
+            std::process::exit(130);
+        }
+    });
+    progress
+}
 
-    if args.len() != 2 {
-        anyhow::bail!("Challenge name not provided")
+/// Installs the binary's subscriber. The library never touches the global
+/// default, and an existing one (e.g. from a test harness) is kept.
+fn init_tracing(quiet: bool) {
+    let builder = tracing_subscriber::FmtSubscriber::builder();
+    let result = if quiet {
+        let subscriber = builder
+            .with_max_level(Level::ERROR)
+            .with_writer(std::io::stderr)
+            .finish();
+        tracing::subscriber::set_global_default(subscriber)
+    } else {
+        let subscriber = builder.with_max_level(Level::DEBUG).finish();
+        tracing::subscriber::set_global_default(subscriber)
+    };
+    if result.is_err() {
+        warn!("a global tracing subscriber is already set, keeping it");
     }
+}
 
-    let response = match args[1].as_str() {
-        HelpMeUnpack::NAME => solve::<HelpMeUnpack>(client).await?,
-        MiniMiner::NAME => solve::<MiniMiner>(client).await?,
-        PasswordHashing::NAME => solve::<PasswordHashing>(client).await?,
-        TalesOfSsl::NAME => solve::<TalesOfSsl>(client).await?,
-        BackupRestore::NAME => solve::<BackupRestore>(client).await?,
-        _ => anyhow::bail!("No such challenge found"),
+async fn solve_challenge(args: SolveArgs, cassette: Option<Cassette>) -> Result<()> {
+    let options = SolveOptions {
+        difficulty_override: args.difficulty_override,
+        validate_schema: args.validate_schema,
+        max_nonce: args.max_nonce,
+        seed: args.seed,
+        dump_request: args.dump_request,
+        resubmit_on_reject: args.resubmit_on_reject,
+        refetch_on_expired: args.refetch_on_expired,
+        answer_renames: args.answer_renames,
+        extra_params: args.params,
+        save_answer: args.save_answer,
+        deadlines: PhaseDeadlines {
+            fetch: args.fetch_deadline,
+            solve: args.solve_deadline,
+            submit: args.submit_deadline,
+        },
+        retry_budget: None,
+        progress: Some(cancel_on_ctrl_c()),
+        cassette,
+        print_answer: args.print_answer,
+        explain_failure: args.explain_failure,
+        bench_serialization: args.bench_serialization,
     };
 
-    info!("{}", response);
+    if args.stdin {
+        let body = read_problem(std::io::stdin().lock())?;
+        return solve_piped(&args.name, &body, args.submit, &options).await;
+    }
+
+    HackatticContext::init()?;
+    let context = HackatticContext::global();
+
+    let client = build_client()?;
+
+    if let Some(addr) = args.metrics_addr {
+        metrics::serve(addr)?;
+    }
+
+    if args.problem_only_json {
+        let answer_json = args.answer_json.as_deref();
+        return solve_raw(
+            &client,
+            context,
+            &args.name,
+            answer_json,
+            args.pretty,
+            &options,
+        )
+        .await;
+    }
+
+    if args.attempts > 1 {
+        let loop_options = LoopOptions {
+            attempts: args.attempts,
+            stop_on_fail: args.stop_on_fail,
+            streak_file: args.streak_file,
+        };
+        let report = looping::run_loop(&loop_options, |_| {
+            let client = client.clone();
+            let (name, options, notify_url) = (&args.name, &options, &args.notify_url);
+            async move {
+                let started = Instant::now();
+                let outcome = solve_named(client.clone(), context, name, options)
+                    .await
+                    .unwrap_or_else(|| Err(HackatticError::NotFound(format!("challenge {name}"))));
+                // one notification per attempt, as a batch sends one per challenge
+                if let Some(url) = notify_url {
+                    let notification = Notification::new(name, &outcome, started.elapsed());
+                    notify(&client, url, &notification).await;
+                }
+                outcome
+            }
+        })
+        .await?;
+        info!(
+            "finished with streak {}, best {}",
+            report.streak.current, report.streak.best
+        );
+        return Ok(());
+    }
+
+    let started = Instant::now();
+    match solve_named(client.clone(), context, &args.name, &options).await {
+        Some(response) => {
+            if let Some(url) = &args.notify_url {
+                let notification = Notification::new(&args.name, &response, started.elapsed());
+                notify(&client, url, &notification).await;
+            }
+            info!("{}", response?)
+        }
+        None => {
+            info!("no solver for {}, fetching the raw problem", args.name);
+            let answer_json = args.answer_json.as_deref();
+            solve_raw(
+                &client,
+                context,
+                &args.name,
+                answer_json,
+                args.pretty,
+                &options,
+            )
+            .await?;
+        }
+    }
 
     Ok(())
 }
 
-async fn solve<T: Hackattic>(client: Client) -> Result<String> {
+async fn solve_batch(
+    names: Vec<String>,
+    notify_url: Option<String>,
+    concurrency: Option<usize>,
+    mut options: SolveOptions,
+    quiet: bool,
+) -> Result<()> {
+    HackatticContext::init()?;
     let context = HackatticContext::global();
-    let mut map = HashMap::new();
-    map.insert("access_token", context.access_token.as_str());
-    if context.playground {
-        map.insert("playground", "1");
+    // every challenge in the batch draws its retries from the same budget
+    options.retry_budget = context.config.batch.retry_budget.map(|retries| {
+        info!("batch retry budget {retries}");
+        Arc::new(RetryBudget::new(retries))
+    });
+
+    let client = build_client()?;
+
+    let concurrency = concurrency.unwrap_or_else(|| batch::default_concurrency(&names));
+    let summary = batch::solve_all(names, concurrency, |name| {
+        let client = client.clone();
+        let options = options.clone();
+        async move {
+            solve_named(client, context, &name, &options)
+                .await
+                .unwrap_or_else(|| Err(HackatticError::NotFound(format!("challenge {name}"))))
+        }
+    })
+    .await;
+
+    match quiet {
+        true => eprintln!("{summary}"),
+        false => println!("{summary}"),
     }
 
-    debug!("{}", T::problem_url());
+    if let Some(url) = &notify_url {
+        for entry in &summary.entries {
+            let notification = Notification::new(&entry.name, &entry.outcome, entry.elapsed);
+            notify(&client, url, &notification).await;
+        }
+    }
 
-    let resp = client.get(T::problem_url()).query(&map).send().await?;
+    if summary.failed() > 0 {
+        anyhow::bail!("{} challenge(s) failed", summary.failed())
+    }
+    Ok(())
+}
 
-    debug!("{:?}", resp);
+/// Solves the named challenge, or `None` if there is no solver for it.
+async fn solve_named(
+    client: Client,
+    context: &HackatticContext,
+    name: &str,
+    options: &SolveOptions,
+) -> Option<Result<String, HackatticError>> {
+    let response = match name {
+        HelpMeUnpack::NAME => solve::<HelpMeUnpack>(client, context, options).await,
+        MiniMiner::NAME => solve::<MiniMiner>(client, context, options).await,
+        PasswordHashing::NAME => solve::<PasswordHashing>(client, context, options).await,
+        TalesOfSsl::NAME => solve::<TalesOfSsl>(client, context, options).await,
+        BackupRestore::NAME => solve::<BackupRestore>(client, context, options).await,
+        _ => return None,
+    };
+    Some(response)
+}
 
-    let body = resp.text().await?;
+/// Solves a problem piped on stdin, printing the answer and only submitting
+/// it if asked to.
+async fn solve_piped(name: &str, body: &str, submit: bool, options: &SolveOptions) -> Result<()> {
+    let offline;
+    let context = if submit {
+        HackatticContext::init()?;
+        HackatticContext::global()
+    } else {
+        offline = HackatticContext::offline()?;
+        &offline
+    };
 
-    debug!("{:?}", body);
+    match name {
+        HelpMeUnpack::NAME => answer_piped::<HelpMeUnpack>(body, context, submit, options).await,
+        MiniMiner::NAME => answer_piped::<MiniMiner>(body, context, submit, options).await,
+        PasswordHashing::NAME => {
+            answer_piped::<PasswordHashing>(body, context, submit, options).await
+        }
+        TalesOfSsl::NAME => answer_piped::<TalesOfSsl>(body, context, submit, options).await,
+        BackupRestore::NAME => answer_piped::<BackupRestore>(body, context, submit, options).await,
+        _ => anyhow::bail!("no solver for {name}, a piped problem can't be solved"),
+    }
+}
 
-    // let problem = resp.json().await?;
-    let problem = serde_json::from_str(&body)?;
+async fn answer_piped<T: Hackattic>(
+    body: &str,
+    context: &HackatticContext,
+    submit: bool,
+    options: &SolveOptions,
+) -> Result<()> {
+    let ans = answer::<T>(body, context, options).await?;
+    println!("{}", serde_json::to_string(&ans)?);
 
-    info!("{:?}", problem);
+    if submit {
+        let client = build_client()?;
+        let response = submit_answer::<T>(&client, context, &ans, options).await?;
+        info!("{}", response);
+    }
+    Ok(())
+}
 
-    let ans = T::solve(problem).await?;
+async fn resubmit_named(name: &str, answer_file: &Path) -> Result<()> {
+    match name {
+        HelpMeUnpack::NAME => resubmit::<HelpMeUnpack>(answer_file).await,
+        MiniMiner::NAME => resubmit::<MiniMiner>(answer_file).await,
+        PasswordHashing::NAME => resubmit::<PasswordHashing>(answer_file).await,
+        TalesOfSsl::NAME => resubmit::<TalesOfSsl>(answer_file).await,
+        BackupRestore::NAME => resubmit::<BackupRestore>(answer_file).await,
+        _ => anyhow::bail!("no solver for {name}, submit a raw answer with --answer-json"),
+    }
+}
 
-    let string = serde_json::to_string(&ans).context("Unable to serialize")?;
+async fn resubmit<T: Hackattic>(answer_file: &Path) -> Result<()> {
+    let ans = load_answer::<T>(answer_file)?;
 
-    info!("{}", string);
+    HackatticContext::init()?;
+    let context = HackatticContext::global();
+    let client = build_client()?;
 
-    let resp = client
-        .post(T::solve_url())
-        .query(&map)
-        .json(&ans)
-        .send()
-        .await?
-        .text()
-        .await?;
+    let response = submit_answer::<T>(&client, context, &ans, &SolveOptions::default()).await?;
+    info!("{}", response);
+    Ok(())
+}
+
+async fn show_problem_diff(name: &str) -> Result<()> {
+    HackatticContext::init()?;
+    let context = HackatticContext::global();
+    let client = build_client()?;
+
+    let diff = diff_problems(&client, context, name, &SolveOptions::default()).await?;
+    if diff.is_empty() {
+        println!("{name} returned the same problem twice");
+    } else {
+        println!("{name} regenerates its problem on every fetch:\n{diff}");
+    }
+    Ok(())
+}
+
+fn login() -> Result<()> {
+    let mut token = String::new();
+    std::io::stdin().read_line(&mut token)?;
+    let token = token.trim();
+    if token.is_empty() {
+        anyhow::bail!("no access token on stdin");
+    }
+    keychain::store_token(token)?;
+    info!("access token stored, set HA_USE_KEYRING=1 to use it");
+    Ok(())
+}
+
+async fn show_status() -> Result<()> {
+    HackatticContext::init()?;
+    let context = HackatticContext::global();
+    let client = build_client()?;
 
-    Ok(resp)
+    let report = status::fetch_status(
+        &client,
+        context,
+        status::KNOWN_CHALLENGES,
+        &SolveOptions::default(),
+    )
+    .await?;
+    println!("{report}");
+    Ok(())
+}
+
+async fn clean(names: Vec<String>) -> Result<()> {
+    let context = HackatticContext::offline()?;
+    for name in names {
+        match name.as_str() {
+            HelpMeUnpack::NAME => HelpMeUnpack::clean(&context).await,
+            MiniMiner::NAME => MiniMiner::clean(&context).await,
+            PasswordHashing::NAME => PasswordHashing::clean(&context).await,
+            TalesOfSsl::NAME => TalesOfSsl::clean(&context).await,
+            BackupRestore::NAME => BackupRestore::clean(&context).await,
+            _ => anyhow::bail!("no solver for {name}, nothing to clean"),
+        }
+        .with_context(|| format!("failed to clean up after {name}"))?;
+        info!("cleaned up after {name}");
+    }
+    Ok(())
+}
+
+async fn solve_raw(
+    client: &Client,
+    context: &HackatticContext,
+    name: &str,
+    answer_json: Option<&Path>,
+    pretty: bool,
+    options: &SolveOptions,
+) -> Result<()> {
+    let problem = fetch_raw_problem(client, context, name, options).await?;
+    if pretty {
+        println!(
+            "{}",
+            pretty::pretty(&problem, std::io::stdout().is_terminal())
+        );
+    } else {
+        println!("{}", serde_json::to_string_pretty(&problem)?);
+    }
+
+    if let Some(path) = answer_json {
+        let answer = fs::read_to_string(path)
+            .with_context(|| format!("unable to read answer {}", path.display()))?;
+        let response = submit_raw_answer(client, context, name, &answer, options).await?;
+        info!("{}", response);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::init_tracing;
+
+    #[test]
+    fn test_init_tracing_twice() {
+        init_tracing(false);
+        init_tracing(true);
+    }
 }
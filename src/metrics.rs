@@ -0,0 +1,271 @@
+use std::{
+    convert::Infallible,
+    fmt::Write,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Response, Server,
+};
+use tracing::{info, warn};
+
+/// Process wide counters, exported in the Prometheus text format by `serve`.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    attempts: AtomicU64,
+    successes: AtomicU64,
+    failures: AtomicU64,
+    /// f64 bits, the last measured mini_miner rate.
+    hash_rate: AtomicU64,
+    request_micros: AtomicU64,
+    /// Requests per `LATENCY_BUCKETS` bucket, the last one for anything slower.
+    latency_buckets: [AtomicU64; LATENCY_BUCKETS.len() + 1],
+    slowest_request: Mutex<Option<(Duration, String)>>,
+}
+
+/// Upper bounds of the request latency histogram's buckets.
+pub const LATENCY_BUCKETS: [Duration; 8] = [
+    Duration::from_millis(50),
+    Duration::from_millis(100),
+    Duration::from_millis(250),
+    Duration::from_millis(500),
+    Duration::from_secs(1),
+    Duration::from_millis(2500),
+    Duration::from_secs(5),
+    Duration::from_secs(10),
+];
+
+static METRICS: Metrics = Metrics::new();
+
+pub fn global() -> &'static Metrics {
+    &METRICS
+}
+
+impl Metrics {
+    pub const fn new() -> Self {
+        Metrics {
+            attempts: AtomicU64::new(0),
+            successes: AtomicU64::new(0),
+            failures: AtomicU64::new(0),
+            hash_rate: AtomicU64::new(0),
+            request_micros: AtomicU64::new(0),
+            latency_buckets: [const { AtomicU64::new(0) }; LATENCY_BUCKETS.len() + 1],
+            slowest_request: Mutex::new(None),
+        }
+    }
+
+    pub fn record_attempt(&self, passed: bool) {
+        self.attempts.fetch_add(1, Ordering::Relaxed);
+        if passed {
+            self.successes.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.failures.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn set_hash_rate(&self, hashes_per_second: f64) {
+        self.hash_rate
+            .store(hashes_per_second.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Records a request's round trip, `request` being what the slowest
+    /// request report calls it.
+    pub fn observe_request(&self, request: &str, elapsed: Duration) {
+        self.request_micros
+            .fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|&bound| elapsed <= bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        self.latency_buckets[bucket].fetch_add(1, Ordering::Relaxed);
+
+        let mut slowest = self.slowest_request.lock().expect("metrics lock poisoned");
+        if slowest.as_ref().is_none_or(|(max, _)| elapsed > *max) {
+            *slowest = Some((elapsed, request.to_string()));
+        }
+    }
+
+    /// Requests per latency bucket, in `LATENCY_BUCKETS` order with the
+    /// slower-than-all count last.
+    pub fn latency_histogram(&self) -> [u64; LATENCY_BUCKETS.len() + 1] {
+        std::array::from_fn(|i| self.latency_buckets[i].load(Ordering::Relaxed))
+    }
+
+    /// The latency histogram and the slowest request, for the end of a run.
+    pub fn latency_report(&self) -> String {
+        let histogram = self.latency_histogram();
+        let total: u64 = histogram.iter().sum();
+        let widest = histogram.iter().copied().max().unwrap_or(0).max(1);
+
+        let mut out = format!("request latency over {total} request(s):");
+        for (i, &count) in histogram.iter().enumerate() {
+            let bucket = match LATENCY_BUCKETS.get(i) {
+                Some(bound) => format!("<= {bound:?}"),
+                None => format!("> {:?}", LATENCY_BUCKETS[i - 1]),
+            };
+            // bars are scaled to 40 columns for the fullest bucket
+            let bar = "#".repeat((count * 40).div_ceil(widest) as usize);
+            let _ = write!(out, "\n  {bucket:>10} {count:>5} {bar}");
+        }
+        if let Some((elapsed, request)) =
+            &*self.slowest_request.lock().expect("metrics lock poisoned")
+        {
+            let _ = write!(out, "\nslowest: {request} took {elapsed:.2?}");
+        }
+        out
+    }
+
+    /// The counters in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        let families = [
+            ("attempts_total", "counter", load(&self.attempts) as f64),
+            ("successes_total", "counter", load(&self.successes) as f64),
+            ("failures_total", "counter", load(&self.failures) as f64),
+            ("hash_rate", "gauge", f64::from_bits(load(&self.hash_rate))),
+        ];
+
+        let mut out = String::new();
+        for (name, kind, value) in families {
+            let _ = writeln!(out, "# TYPE hackattic_{name} {kind}");
+            let _ = writeln!(out, "hackattic_{name} {value}");
+        }
+
+        // buckets are cumulative, each counting every request at most its bound
+        let name = "hackattic_request_duration_seconds";
+        let _ = writeln!(out, "# TYPE {name} histogram");
+        let histogram = self.latency_histogram();
+        let mut cumulative = 0;
+        for (bound, count) in LATENCY_BUCKETS.iter().zip(histogram) {
+            cumulative += count;
+            let le = bound.as_secs_f64();
+            let _ = writeln!(out, "{name}_bucket{{le=\"{le}\"}} {cumulative}");
+        }
+        let count = cumulative + histogram[LATENCY_BUCKETS.len()];
+        let _ = writeln!(out, "{name}_bucket{{le=\"+Inf\"}} {count}");
+        let sum = load(&self.request_micros) as f64 / 1e6;
+        let _ = writeln!(out, "{name}_sum {sum}");
+        let _ = writeln!(out, "{name}_count {count}");
+        out
+    }
+}
+
+/// Serves the global metrics on `addr` in the background, returning the
+/// address actually bound.
+pub fn serve(addr: SocketAddr) -> Result<SocketAddr> {
+    let make_service = make_service_fn(|_| async {
+        Ok::<_, Infallible>(service_fn(|_request| async {
+            Ok::<_, Infallible>(Response::new(Body::from(global().render())))
+        }))
+    });
+
+    let server = Server::try_bind(&addr)
+        .with_context(|| format!("failed to bind metrics endpoint on {addr}"))?
+        .serve(make_service);
+    let local_addr = server.local_addr();
+    info!("serving metrics on http://{local_addr}/metrics");
+
+    tokio::spawn(async move {
+        if let Err(e) = server.await {
+            warn!("metrics endpoint stopped: {e}");
+        }
+    });
+    Ok(local_addr)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use wiremock::{matchers::path, Mock, MockServer, ResponseTemplate};
+
+    use super::{global, serve, LATENCY_BUCKETS};
+    use crate::{config::Config, fetch_raw_problem, test_util, SolveOptions};
+
+    #[tokio::test]
+    async fn test_endpoint_serves_counters() {
+        global().record_attempt(true);
+        global().record_attempt(false);
+        global().observe_request("GET /problem/", Duration::from_millis(250));
+
+        let addr = serve("127.0.0.1:0".parse().unwrap()).unwrap();
+        let body = reqwest::get(format!("http://{addr}/metrics"))
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+
+        for name in [
+            "hackattic_attempts_total",
+            "hackattic_successes_total",
+            "hackattic_failures_total",
+            "hackattic_hash_rate",
+            "hackattic_request_duration_seconds",
+        ] {
+            assert!(body.contains(&format!("# TYPE {name} ")), "{body}");
+        }
+        assert!(
+            body.contains("# TYPE hackattic_request_duration_seconds histogram\n"),
+            "{body}"
+        );
+        let sample = |prefix: &str| {
+            let value = body.lines().find_map(|line| line.strip_prefix(prefix));
+            value.unwrap().parse::<f64>().unwrap()
+        };
+        // the 250ms request is in its own bucket and every wider one
+        assert!(sample("hackattic_request_duration_seconds_bucket{le=\"0.25\"} ") >= 1.0);
+        let count = sample("hackattic_request_duration_seconds_count ");
+        assert_eq!(
+            sample("hackattic_request_duration_seconds_bucket{le=\"+Inf\"} "),
+            count
+        );
+        assert!(sample("hackattic_request_duration_seconds_sum ") >= 0.25);
+        let attempts = body
+            .lines()
+            .find_map(|line| line.strip_prefix("hackattic_attempts_total "))
+            .unwrap();
+        assert!(attempts.parse::<f64>().unwrap() >= 2.0, "{body}");
+    }
+
+    #[tokio::test]
+    async fn test_latency_histogram_fills_from_requests() {
+        let server = MockServer::start().await;
+        Mock::given(path("/challenges/slow/problem/"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string("{}")
+                    .set_delay(Duration::from_millis(300)),
+            )
+            .mount(&server)
+            .await;
+        let context = test_util::context(&server.uri(), Config::default());
+
+        let before = global().latency_histogram();
+        let client = reqwest::Client::new();
+        for _ in 0..3 {
+            fetch_raw_problem(&client, &context, "slow", &SolveOptions::default())
+                .await
+                .unwrap();
+        }
+
+        // other tests may add requests of their own, never remove any
+        let after = global().latency_histogram();
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|&bound| bound == Duration::from_millis(500))
+            .unwrap();
+        assert!(after[bucket] - before[bucket] >= 3, "{after:?}");
+        let report = global().latency_report();
+        assert!(report.contains("<= 500ms"), "{report}");
+        assert!(report.contains("slowest: GET "), "{report}");
+    }
+}
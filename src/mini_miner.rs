@@ -1,21 +1,189 @@
-use super::Hackattic;
+use super::{
+    config::ChallengeConfig, encoding::hex_encode, hackattic_context::HackatticContext, metrics,
+    progress::Progress, About, Hackattic, HackatticError, SolveOptions,
+};
 use anyhow::{Context, Result};
-use rayon::prelude::*;
+use rayon::{prelude::*, ThreadPool, ThreadPoolBuilder};
 use serde::{Deserialize, Serialize};
 use serde_tuple::{Deserialize_tuple, Serialize_tuple};
 use sha2::{
-    digest::generic_array::{typenum, GenericArray},
-    Digest, Sha256,
+    digest::{
+        generic_array::{typenum, GenericArray},
+        Output,
+    },
+    Digest, Sha256, Sha512,
+};
+use std::{
+    fs,
+    hint::black_box,
+    io::Write,
+    marker::PhantomData,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
 };
-use std::sync::Arc;
-use tracing::info;
+use tracing::{info, warn};
 
+/// `{"difficulty": 8, "block": {"data": [["<data>", <nonce>], ...], "nonce": null}}`
 #[derive(Deserialize, Debug)]
 pub struct MiniMinerProblem {
     pub difficulty: u32,
     pub block: Block,
+    /// How to mine, from the config file and `SolveOptions` rather than
+    /// the server.
+    #[serde(skip)]
+    pub settings: MiniMinerSettings,
+}
+
+#[derive(Debug, Default)]
+pub struct MiniMinerSettings {
+    /// Highest nonce tried, set from `SolveOptions::max_nonce`.
+    pub max_nonce: Option<i32>,
+    /// Worker threads for the search, set from the config file.
+    pub threads: Threads,
+    /// How long the search should take at most, from the config file. Only
+    /// warned about when the work estimate exceeds it.
+    pub deadline: Option<Duration>,
+    /// Where the winning block is saved, next to `SolveOptions::save_answer`.
+    pub block_file: Option<PathBuf>,
+    /// How blocks are written out for hashing, from the config file.
+    pub serialization: Serialization,
+    /// What serialized blocks are hashed with, from the config file.
+    pub digest: BlockDigest,
+    /// Counts searched nonces against the expected number of hashes.
+    pub progress: Progress,
+    /// Compares naive and templated serialization before mining, set from
+    /// `SolveOptions::bench_serialization`.
+    pub bench_serialization: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Threads {
+    #[default]
+    AllCores,
+    Fixed(usize),
+    Adaptive(ScalingPolicy),
+}
+
+/// Starts the search on a few threads and adds more while no nonce turns
+/// up, leaving cores to whatever else is running if the block is easy.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct ScalingPolicy {
+    #[serde(default = "ScalingPolicy::default_start_threads")]
+    pub start_threads: usize,
+    #[serde(default = "ScalingPolicy::default_max_threads")]
+    pub max_threads: usize,
+    /// How long each thread count gets to find a nonce before doubling.
+    #[serde(default = "ScalingPolicy::default_window_secs")]
+    pub window_secs: f64,
+}
+
+impl ScalingPolicy {
+    fn default_start_threads() -> usize {
+        1
+    }
+
+    fn default_max_threads() -> usize {
+        std::thread::available_parallelism().map_or(1, |n| n.get())
+    }
+
+    fn default_window_secs() -> f64 {
+        2.0
+    }
+}
+
+/// Tracks an adaptive search's thread count.
+#[derive(Debug)]
+pub struct ThreadScaler {
+    policy: ScalingPolicy,
+    window: Duration,
+    threads: usize,
+    since: Instant,
+}
+
+impl ThreadScaler {
+    /// A `window_secs` that isn't a valid duration never scales, `configure`
+    /// rejects those from the config file up front.
+    pub fn new(policy: ScalingPolicy, now: Instant) -> Self {
+        ThreadScaler {
+            threads: policy.start_threads.clamp(1, policy.max_threads.max(1)),
+            window: Duration::try_from_secs_f64(policy.window_secs).unwrap_or(Duration::MAX),
+            policy,
+            since: now,
+        }
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// Doubles the thread count, up to the policy's maximum, once a window
+    /// has passed without a hit. Returns whether it changed.
+    pub fn tick(&mut self, now: Instant) -> bool {
+        if self.threads >= self.policy.max_threads || now.duration_since(self.since) < self.window {
+            return false;
+        }
+        self.threads = (self.threads * 2).min(self.policy.max_threads);
+        self.since = now;
+        true
+    }
 }
 
+/// `[challenges.mini_miner]` in the config file. A `[challenges.mini_miner.scaling]`
+/// table takes precedence over a fixed `threads`.
+#[derive(Deserialize, Debug, Default)]
+pub struct MiniMinerConfig {
+    pub threads: Option<usize>,
+    pub scaling: Option<ScalingPolicy>,
+    pub deadline_secs: Option<f64>,
+    #[serde(default)]
+    pub serialization: Serialization,
+    #[serde(default)]
+    pub digest: BlockDigest,
+}
+
+/// `serialization` in the config file. The server hashes compact JSON, the
+/// others are for adapting if its format ever changes.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Serialization {
+    #[default]
+    Compact,
+    /// serde_json's pretty printing, two space indents
+    Pretty,
+}
+
+impl Serialization {
+    pub fn serialize(&self, block: &Block) -> Vec<u8> {
+        match self {
+            Serialization::Compact => serialize_block(block),
+            Serialization::Pretty => serde_json::to_vec_pretty(block).expect("Unable to serialize"),
+        }
+    }
+}
+
+/// `digest` in the config file. The server uses SHA-256, SHA-512 is there
+/// for variants of the challenge and experiments.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BlockDigest {
+    #[default]
+    Sha256,
+    Sha512,
+}
+
+impl ChallengeConfig for MiniMinerConfig {
+    const CHALLENGE: &'static str = MiniMiner::NAME;
+}
+
+/// The nonce stays an `i32` like the data's, which the server fills with
+/// negative values too, but only `0..=i32::MAX` is ever searched: below 28
+/// bits of difficulty the chance that none of those 2^31 nonces passes is
+/// under e^-8, and no difficulty the server hands out comes close.
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Block {
     pub data: Arc<Vec<Data>>,
@@ -31,13 +199,17 @@ impl Block {
     }
 }
 
+/// On the wire a pair, `["x",5]`, data first. A block is then
+/// `{"data":[["x",5],...],"nonce":45}`, fields in that order and no
+/// whitespace; the hash is over exactly these bytes, so neither the derive
+/// nor the field order here may change.
 #[derive(Serialize_tuple, Deserialize_tuple, Debug, Clone)]
 pub struct Data {
     pub data: String,
     pub nonce: i32,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 #[serde(rename = "")]
 pub struct MiniMinerAnswer {
     pub nonce: i32,
@@ -45,29 +217,554 @@ pub struct MiniMinerAnswer {
 
 pub struct MiniMiner;
 
+const HASH_BITS: u32 = 256;
+
+/// What a difficulty value counts. Getting this wrong means every nonce is
+/// rejected, so the unit the challenge uses is spelled out below.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DifficultyUnit {
+    /// leading zero bits
+    Bits,
+    /// leading zero hex digits, i.e. nibbles
+    HexDigits,
+    /// leading zero bytes
+    Bytes,
+}
+
+impl DifficultyUnit {
+    pub fn bits_per_unit(&self) -> u32 {
+        match self {
+            DifficultyUnit::Bits => 1,
+            DifficultyUnit::HexDigits => 4,
+            DifficultyUnit::Bytes => 8,
+        }
+    }
+
+    pub fn to_bits(&self, difficulty: u32) -> u32 {
+        difficulty.saturating_mul(self.bits_per_unit())
+    }
+
+    /// Expresses `difficulty` in another unit, rounding up so the converted
+    /// value is never easier than the original.
+    pub fn convert(&self, difficulty: u32, to: DifficultyUnit) -> u32 {
+        self.to_bits(difficulty).div_ceil(to.bits_per_unit())
+    }
+}
+
+/// mini_miner's difficulty is the number of leading zero bits of the hash.
+pub const DIFFICULTY_UNIT: DifficultyUnit = DifficultyUnit::Bits;
+
 impl Hackattic for MiniMiner {
     const NAME: &'static str = "mini_miner";
+    const ABOUT: About = About {
+        summary: "Find a nonce that makes the SHA-256 of the block's compact JSON start with `difficulty` zero bits.",
+        problem: r#"{"difficulty": 8, "block": {"data": [["<data>", <nonce>], ...], "nonce": null}}"#,
+        prerequisites: &[],
+    };
+    const RESUBMITTABLE: bool = false;
     type Problem = MiniMinerProblem;
     type Answer = MiniMinerAnswer;
 
+    fn answer_schema() -> Option<serde_json::Value> {
+        Some(serde_json::json!({
+            "type": "object",
+            "required": ["nonce"],
+            "properties": {"nonce": {"type": "integer"}}
+        }))
+    }
+
+    fn override_problem(problem: &mut Self::Problem, options: &SolveOptions) {
+        if let Some(difficulty) = options.difficulty_override {
+            info!(
+                "overriding difficulty {} with {}",
+                problem.difficulty, difficulty
+            );
+            problem.difficulty = difficulty;
+        }
+    }
+
+    fn configure(problem: &mut Self::Problem, context: &HackatticContext) -> Result<()> {
+        let config = context.challenge_config::<MiniMinerConfig>()?;
+        if let Some(policy) = &config.scaling {
+            Duration::try_from_secs_f64(policy.window_secs)
+                .context("invalid mini_miner scaling window_secs")?;
+        }
+        problem.settings.threads = match (config.scaling, config.threads) {
+            (Some(policy), _) => Threads::Adaptive(policy),
+            (None, Some(threads)) => Threads::Fixed(threads),
+            (None, None) => Threads::AllCores,
+        };
+        problem.settings.deadline = config
+            .deadline_secs
+            .map(Duration::try_from_secs_f64)
+            .transpose()
+            .context("invalid mini_miner deadline_secs")?;
+        problem.settings.serialization = config.serialization;
+        problem.settings.digest = config.digest;
+        Ok(())
+    }
+
+    fn apply_options(problem: &mut Self::Problem, options: &SolveOptions) {
+        problem.settings.max_nonce = options.max_nonce;
+        problem.settings.block_file = options.save_answer.as_deref().map(block_file_for);
+        problem.settings.progress = options.progress.clone().unwrap_or_default();
+        problem.settings.bench_serialization = options.bench_serialization;
+    }
+
     async fn solve(problem: Self::Problem) -> Result<Self::Answer> {
-        let found_block = (0..=i32::MAX)
-            .into_par_iter()
-            .map(|nonce| problem.block.with_nonce(nonce))
-            .find_any(|block| is_block_valid(block, problem.difficulty));
+        if problem.settings.bench_serialization {
+            let bench = bench_serialization(&problem.block, BENCH_NONCES)?;
+            info!(
+                "{} nonces hash alike either way, naive {:?}, templated {:?}, {:.2}x faster",
+                bench.nonces,
+                bench.naive,
+                bench.templated,
+                bench.speedup()
+            );
+        }
+        let estimate = estimate_work(&problem)?;
+        info!(
+            "difficulty {} needs ~{:.0} hashes, about {:?} at {:.0} hashes/s",
+            problem.difficulty, estimate.expected_hashes, estimate.eta, estimate.hash_rate
+        );
+        if let Some(deadline) = problem
+            .settings
+            .deadline
+            .filter(|&deadline| estimate.eta > deadline)
+        {
+            warn!(
+                "expected to take {:?}, longer than the {deadline:?} deadline",
+                estimate.eta
+            );
+        }
 
-        info!("{found_block:?}");
+        let mined = mine(&problem)?;
 
-        if let Some(valid_block) = found_block {
-            Ok(MiniMinerAnswer {
-                nonce: valid_block.nonce.context("nonce is None")?,
-            })
-        } else {
-            anyhow::bail!("No block found")
+        info!(
+            nonce = mined.nonce,
+            block = %mined.serialized,
+            hash = %mined.hash,
+            "found block"
+        );
+        if let Some(path) = &problem.settings.block_file {
+            save_block(path, problem.block.with_nonce(mined.nonce), mined.hash)?;
+        }
+
+        Ok(MiniMinerAnswer { nonce: mined.nonce })
+    }
+}
+
+/// The winning block with what it hashed to, so a solve can be checked by
+/// hand. Only the nonce is submitted.
+#[derive(Debug)]
+pub struct MinedBlock {
+    pub nonce: i32,
+    pub serialized: String,
+    pub hash: String,
+}
+
+/// The winning block as saved to a file, so the solve can be verified by a
+/// script that rehashes `block`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BlockCapture {
+    pub block: Block,
+    pub hash: String,
+}
+
+/// `answer.json` keeps its winning block in `answer.block.json`.
+pub fn block_file_for(answer_file: &Path) -> PathBuf {
+    answer_file.with_extension("block.json")
+}
+
+pub fn save_block(path: &Path, block: Block, hash: String) -> Result<()> {
+    let json = serde_json::to_string_pretty(&BlockCapture { block, hash })
+        .context("Unable to serialize")?;
+    fs::write(path, json)
+        .with_context(|| format!("unable to save the winning block to {}", path.display()))?;
+    info!("saved the winning block to {}", path.display());
+    Ok(())
+}
+
+/// Turns a block into the bytes that get hashed.
+pub trait BlockSerializer: Fn(&Block) -> Vec<u8> + Send + Sync {}
+
+impl<F: Fn(&Block) -> Vec<u8> + Send + Sync> BlockSerializer for F {}
+
+/// Compact JSON in field order, which is what the server hashes.
+pub fn serialize_block(block: &Block) -> Vec<u8> {
+    serde_json::to_vec(block).expect("Unable to serialize")
+}
+
+/// A block's compact JSON split around its nonce, `{"data":[...],"nonce":`
+/// and `}`. Only the nonce changes between candidates, so writing it between
+/// the two serializes a candidate without going through serde for the data
+/// every time. Only valid for blocks with the data it was made from.
+#[derive(Debug, Clone)]
+pub struct BlockTemplate {
+    prefix: Vec<u8>,
+}
+
+const TEMPLATE_SUFFIX: &[u8] = b"}";
+
+impl BlockTemplate {
+    pub fn new(block: &Block) -> Self {
+        let serialized = serialize_block(&Block {
+            data: Arc::clone(&block.data),
+            nonce: None,
+        });
+        let prefix = serialized
+            .strip_suffix(b"null}")
+            .expect("compact blocks end with their nonce")
+            .to_vec();
+        BlockTemplate { prefix }
+    }
+
+    /// The same bytes as `serialize_block` for the block with `nonce`.
+    pub fn serialize(&self, nonce: Option<i32>) -> Vec<u8> {
+        // an i32 is at most 11 characters
+        let mut bytes = Vec::with_capacity(self.prefix.len() + 11 + TEMPLATE_SUFFIX.len());
+        bytes.extend_from_slice(&self.prefix);
+        match nonce {
+            Some(nonce) => write!(bytes, "{nonce}").expect("writing to a Vec can't fail"),
+            None => bytes.extend_from_slice(b"null"),
         }
+        bytes.extend_from_slice(TEMPLATE_SUFFIX);
+        bytes
+    }
+}
+
+/// Nonces `--bench-serialization` hashes each way.
+pub const BENCH_NONCES: i32 = 100_000;
+
+/// How long hashing the same candidates took serialized through serde and
+/// from a `BlockTemplate`.
+#[derive(Debug)]
+pub struct SerializationBench {
+    pub nonces: i32,
+    pub naive: Duration,
+    pub templated: Duration,
+}
+
+impl SerializationBench {
+    pub fn speedup(&self) -> f64 {
+        self.naive.as_secs_f64() / self.templated.as_secs_f64().max(f64::EPSILON)
     }
 }
 
+/// Hashes the block with nonces `0..nonces` both ways on one thread, failing
+/// at the first nonce they disagree on.
+pub fn bench_serialization(block: &Block, nonces: i32) -> Result<SerializationBench> {
+    let started = Instant::now();
+    let naive: Vec<_> = (0..nonces)
+        .map(|nonce| calculate_sha256(serialize_block(&block.with_nonce(nonce))))
+        .collect();
+    let naive_elapsed = started.elapsed();
+
+    let template = BlockTemplate::new(block);
+    let started = Instant::now();
+    let templated: Vec<_> = (0..nonces)
+        .map(|nonce| calculate_sha256(template.serialize(Some(nonce))))
+        .collect();
+    let templated_elapsed = started.elapsed();
+
+    if let Some((nonce, _)) = naive
+        .iter()
+        .zip(&templated)
+        .enumerate()
+        .find(|(_, (naive, templated))| naive != templated)
+    {
+        anyhow::bail!("naive and templated serialization disagree at nonce {nonce}");
+    }
+    Ok(SerializationBench {
+        nonces,
+        naive: naive_elapsed,
+        templated: templated_elapsed,
+    })
+}
+
+pub fn mine(problem: &MiniMinerProblem) -> Result<MinedBlock> {
+    let serialize = configured_serializer(problem);
+    match problem.settings.digest {
+        BlockDigest::Sha256 => mine_with(problem, serialize),
+        BlockDigest::Sha512 => mine_with_hasher(problem, serialize, digest::<Sha512>),
+    }
+}
+
+/// Serializes blocks the way `serialization` asks, the server's compact
+/// JSON from a `BlockTemplate` of the problem's block.
+fn configured_serializer(problem: &MiniMinerProblem) -> impl BlockSerializer + '_ {
+    // the search only ever changes the nonce of the problem's block
+    let template = (problem.settings.serialization == Serialization::Compact)
+        .then(|| BlockTemplate::new(&problem.block));
+    move |block: &Block| match &template {
+        Some(template) => template.serialize(block.nonce),
+        None => problem.settings.serialization.serialize(block),
+    }
+}
+
+/// Hashes a serialized block. The challenge uses SHA-256, any `Digest` fits
+/// through `digest::<D>`.
+pub trait BlockHasher<H: AsRef<[u8]>>: Fn(&[u8]) -> H + Send + Sync {}
+
+impl<H: AsRef<[u8]>, F: Fn(&[u8]) -> H + Send + Sync> BlockHasher<H> for F {}
+
+pub fn digest<D: Digest>(bytes: &[u8]) -> Output<D> {
+    D::digest(bytes)
+}
+
+/// Mines with a custom serialization, to chase down mismatches with the
+/// server's.
+pub fn mine_with(
+    problem: &MiniMinerProblem,
+    serialize: impl BlockSerializer,
+) -> Result<MinedBlock> {
+    mine_with_hasher(problem, serialize, digest::<Sha256>)
+}
+
+/// Mines with a custom serialization and hash, for challenge variants or to
+/// test the search against a stub.
+pub fn mine_with_hasher<H: AsRef<[u8]>>(
+    problem: &MiniMinerProblem,
+    serialize: impl BlockSerializer,
+    hash: impl BlockHasher<H>,
+) -> Result<MinedBlock> {
+    let mut search = MiniMinerSearch::with_hasher(problem, serialize, hash)?;
+    let started = Instant::now();
+    let progress = &problem.settings.progress;
+    progress.set_total(expected_hashes(problem.difficulty) as u64);
+    let mut best_zeros = 0;
+    let mut hashed = 0u64;
+    let mut step = |search: &mut MiniMinerSearch<_, _, _>| match search.next() {
+        Some(SearchStep::Found(mined)) => Some(Ok(mined)),
+        Some(SearchStep::Miss {
+            searched,
+            best_zeros: best,
+        }) => {
+            best_zeros = best_zeros.max(best);
+            let count = (searched.end() - searched.start()) as u64 + 1;
+            progress.advance(count);
+            // scraped while mining, not just once the nonce is found
+            hashed += count;
+            metrics::global().set_hash_rate(hashed as f64 / started.elapsed().as_secs_f64());
+            progress.check().err().map(Err)
+        }
+        None => Some(Err(HackatticError::NotFound(format!(
+            "no nonce in 0..={} has {} leading zero bits, the best had {best_zeros}",
+            search.max_nonce, search.difficulty
+        )))),
+    };
+    let found = match problem.settings.threads {
+        Threads::AllCores => loop {
+            if let Some(found) = step(&mut search) {
+                break found;
+            }
+        },
+        Threads::Fixed(threads) => {
+            let pool = thread_pool(threads)?;
+            loop {
+                if let Some(found) = pool.install(|| step(&mut search)) {
+                    break found;
+                }
+            }
+        }
+        Threads::Adaptive(policy) => {
+            let mut scaler = ThreadScaler::new(policy, started);
+            let mut pool = thread_pool(scaler.threads())?;
+            loop {
+                if let Some(found) = pool.install(|| step(&mut search)) {
+                    break found;
+                }
+                if scaler.tick(Instant::now()) {
+                    info!(
+                        "no nonce below {} yet, mining on {} threads",
+                        search.next_nonce(),
+                        scaler.threads()
+                    );
+                    pool = thread_pool(scaler.threads())?;
+                }
+            }
+        }
+    };
+
+    let mined = found?;
+
+    // find_first has checked at least every nonce before the winning one
+    metrics::global().set_hash_rate(f64::from(mined.nonce) / started.elapsed().as_secs_f64());
+    Ok(mined)
+}
+
+/// What one step of a `MiniMinerSearch` turned up.
+#[derive(Debug)]
+pub enum SearchStep {
+    /// No nonce in `searched` passed, the closest had `best_zeros` leading
+    /// zero bits.
+    Miss {
+        searched: RangeInclusive<i32>,
+        best_zeros: u32,
+    },
+    Found(MinedBlock),
+}
+
+/// The nonce search as an iterator over chunks of nonces, for library users
+/// that want their own stopping criteria or progress display around it.
+/// Each chunk is searched in parallel on the current rayon pool, and the
+/// iterator ends after a hit or once `max_nonce` has been searched.
+pub struct MiniMinerSearch<'a, S, F, H> {
+    problem: &'a MiniMinerProblem,
+    serialize: S,
+    hash: F,
+    digest: PhantomData<fn() -> H>,
+    difficulty: u32,
+    max_nonce: i32,
+    next: Option<i32>,
+}
+
+type Sha256Search<'a> =
+    MiniMinerSearch<'a, fn(&Block) -> Vec<u8>, fn(&[u8]) -> Output<Sha256>, Output<Sha256>>;
+
+impl<'a> Sha256Search<'a> {
+    /// Searches with the server's serialization and SHA-256.
+    pub fn new(problem: &'a MiniMinerProblem) -> Result<Self> {
+        Self::with_hasher(problem, serialize_block, digest::<Sha256>)
+    }
+}
+
+impl<'a, S: BlockSerializer, H: AsRef<[u8]>, F: BlockHasher<H>> MiniMinerSearch<'a, S, F, H> {
+    pub fn with_hasher(problem: &'a MiniMinerProblem, serialize: S, hash: F) -> Result<Self> {
+        let difficulty = DIFFICULTY_UNIT.to_bits(problem.difficulty);
+        let hash_bits = hash(&serialize(&problem.block)).as_ref().len() as u32 * 8;
+        if difficulty > hash_bits {
+            return Err(HackatticError::NotFound(format!(
+                "no nonce can satisfy difficulty {}",
+                problem.difficulty
+            ))
+            .into());
+        }
+        Ok(MiniMinerSearch {
+            problem,
+            serialize,
+            hash,
+            digest: PhantomData,
+            difficulty,
+            max_nonce: problem.settings.max_nonce.unwrap_or(i32::MAX),
+            next: Some(0),
+        })
+    }
+
+    /// Skips the nonces below `nonce`, e.g. ones an earlier search already
+    /// ruled out. Negative nonces are never searched, see `Block`.
+    pub fn starting_at(mut self, nonce: i32) -> Self {
+        self.next = Some(nonce.max(0));
+        self
+    }
+
+    /// The first nonce the next step searches, or past the end once done.
+    pub fn next_nonce(&self) -> i64 {
+        self.next.map_or(i64::from(self.max_nonce) + 1, i64::from)
+    }
+}
+
+/// Nonces searched per step, also how often an adaptive search checks its
+/// thread count.
+const SEARCH_CHUNK: i32 = 1 << 16;
+
+impl<S: BlockSerializer, H: AsRef<[u8]>, F: BlockHasher<H>> Iterator
+    for MiniMinerSearch<'_, S, F, H>
+{
+    type Item = SearchStep;
+
+    fn next(&mut self) -> Option<SearchStep> {
+        let start = self.next.take().filter(|&start| start <= self.max_nonce)?;
+        let end = start.saturating_add(SEARCH_CHUNK - 1).min(self.max_nonce);
+
+        let best_zeros = AtomicU32::new(0);
+        let found = (start..=end)
+            .into_par_iter()
+            .map(|nonce| self.problem.block.with_nonce(nonce))
+            // find_first keeps the answer reproducible for the same problem
+            .find_first(|block| {
+                let hash = (self.hash)(&(self.serialize)(block));
+                let hash = hash.as_ref();
+                best_zeros.fetch_max(leading_zero_bits(hash), Ordering::Relaxed);
+                check_difficulty(hash, self.difficulty)
+            });
+
+        let Some(block) = found else {
+            self.next = end.checked_add(1);
+            return Some(SearchStep::Miss {
+                searched: start..=end,
+                best_zeros: best_zeros.into_inner(),
+            });
+        };
+
+        let bytes = (self.serialize)(&block);
+        Some(SearchStep::Found(MinedBlock {
+            nonce: block.nonce.expect("searched blocks have a nonce"),
+            hash: hex_encode((self.hash)(&bytes).as_ref()),
+            serialized: String::from_utf8_lossy(&bytes).into_owned(),
+        }))
+    }
+}
+
+/// Nonces hashed to measure the hash rate before mining.
+const PROBE_NONCES: i32 = 4096;
+
+/// What mining a problem is expected to take.
+#[derive(Debug)]
+pub struct WorkEstimate {
+    pub expected_hashes: f64,
+    pub hash_rate: f64,
+    pub eta: Duration,
+}
+
+/// Each hash has the difficulty's leading zero bits with probability
+/// 2^-bits, so that many hashes are needed on average.
+pub fn expected_hashes(difficulty: u32) -> f64 {
+    2f64.powi(DIFFICULTY_UNIT.to_bits(difficulty).min(HASH_BITS) as i32)
+}
+
+/// Measures the hash rate with the threads the search will end up using.
+pub fn estimate_work(problem: &MiniMinerProblem) -> Result<WorkEstimate> {
+    // serialized and hashed like `mine` does, SHA-512 is a good deal slower
+    // per block and the template a good deal faster
+    let serialize = configured_serializer(problem);
+    let probe = || match problem.settings.digest {
+        BlockDigest::Sha256 => probe_hash_rate(&problem.block, &serialize, digest::<Sha256>),
+        BlockDigest::Sha512 => probe_hash_rate(&problem.block, &serialize, digest::<Sha512>),
+    };
+    let hash_rate = match problem.settings.threads {
+        Threads::AllCores => probe(),
+        Threads::Fixed(threads) => thread_pool(threads)?.install(probe),
+        Threads::Adaptive(policy) => thread_pool(policy.max_threads)?.install(probe),
+    };
+    let expected_hashes = expected_hashes(problem.difficulty);
+    Ok(WorkEstimate {
+        expected_hashes,
+        hash_rate,
+        eta: Duration::try_from_secs_f64(expected_hashes / hash_rate).unwrap_or(Duration::MAX),
+    })
+}
+
+/// Hashes per second over the block's first `PROBE_NONCES` nonces.
+pub fn probe_hash_rate<H: AsRef<[u8]>>(
+    block: &Block,
+    serialize: impl BlockSerializer,
+    hash: impl BlockHasher<H>,
+) -> f64 {
+    let started = Instant::now();
+    (0..PROBE_NONCES).into_par_iter().for_each(|nonce| {
+        black_box(hash(&serialize(&block.with_nonce(nonce))));
+    });
+    f64::from(PROBE_NONCES) / started.elapsed().as_secs_f64().max(f64::EPSILON)
+}
+
+fn thread_pool(threads: usize) -> Result<ThreadPool> {
+    ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .context("failed to start mining threads")
+}
+
 fn check_difficulty(hash: &[u8], mut difficulty: u32) -> bool {
     let mut index = 0;
     while difficulty > 0 && index < hash.len() {
@@ -84,6 +781,15 @@ fn check_difficulty(hash: &[u8], mut difficulty: u32) -> bool {
     difficulty == 0
 }
 
+/// Like `check_difficulty`, but a miss says how many more leading zero bits
+/// the hash needed, to tell near misses from serialization bugs.
+pub fn difficulty_shortfall(hash: &[u8], difficulty: u32) -> Result<(), u32> {
+    match leading_zero_bits(hash) {
+        zeros if zeros >= difficulty => Ok(()),
+        zeros => Err(difficulty - zeros),
+    }
+}
+
 fn get_mask(difficulty: u32) -> u8 {
     match difficulty {
         0 => 0b0000_0000,
@@ -98,24 +804,57 @@ fn get_mask(difficulty: u32) -> u8 {
     }
 }
 
-fn calculate_sha256(s: String) -> GenericArray<u8, typenum::U32> {
+fn calculate_sha256(bytes: impl AsRef<[u8]>) -> GenericArray<u8, typenum::U32> {
     let mut hasher = Sha256::new();
-    hasher.update(s.into_bytes());
+    hasher.update(bytes);
     hasher.finalize()
 }
 
-fn is_block_valid(block: &Block, difficulty: u32) -> bool {
-    let s = serde_json::to_string(block).expect("Unable to serialize");
-    let hash = calculate_sha256(s);
-
-    check_difficulty(hash.as_ref(), difficulty)
+fn leading_zero_bits(hash: &[u8]) -> u32 {
+    match hash.iter().position(|&byte| byte != 0) {
+        Some(index) => index as u32 * 8 + hash[index].leading_zeros(),
+        None => hash.len() as u32 * 8,
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Block;
-    use super::{calculate_sha256, check_difficulty};
+    use super::{
+        block_file_for, Block, BlockCapture, BlockDigest, Data, MiniMiner, MiniMinerConfig,
+        MiniMinerProblem, MiniMinerSearch, MiniMinerSettings, ScalingPolicy, SearchStep,
+        Serialization, ThreadScaler, Threads, SEARCH_CHUNK,
+    };
+    use super::{
+        calculate_sha256, check_difficulty, difficulty_shortfall, digest, estimate_work,
+        expected_hashes, leading_zero_bits, mine, mine_with, mine_with_hasher, probe_hash_rate,
+        serialize_block, DifficultyUnit,
+    };
+    use crate::{
+        config::Config, hackattic_context::HackatticContext, progress::Progress, solve, test_util,
+        Hackattic, HackatticError, SolveOptions,
+    };
+    use sha2::{Digest, Sha256, Sha512};
     use std::sync::Arc;
+    use std::time::{Duration, Instant};
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    fn new_problem(
+        difficulty: u32,
+        data: Vec<Data>,
+        settings: MiniMinerSettings,
+    ) -> MiniMinerProblem {
+        MiniMinerProblem {
+            difficulty,
+            block: Block {
+                data: Arc::new(data),
+                nonce: None,
+            },
+            settings,
+        }
+    }
 
     #[test]
     fn test_check_difficulty() {
@@ -130,6 +869,35 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_difficulty_units() {
+        let hash = [0x00, 0x0F, 0xFF];
+
+        // the same numeric difficulty means 3, 12 and 24 zero bits
+        assert!(check_difficulty(&hash, DifficultyUnit::Bits.to_bits(3)));
+        assert!(check_difficulty(
+            &hash,
+            DifficultyUnit::HexDigits.to_bits(3)
+        ));
+        assert!(!check_difficulty(&hash, DifficultyUnit::Bytes.to_bits(3)));
+
+        assert!(check_difficulty(&hash, DifficultyUnit::Bytes.to_bits(1)));
+        assert!(!check_difficulty(
+            &hash,
+            DifficultyUnit::HexDigits.to_bits(4)
+        ));
+    }
+
+    #[test]
+    fn test_difficulty_unit_conversion() {
+        use DifficultyUnit::*;
+        assert_eq!(HexDigits.convert(3, Bits), 12);
+        assert_eq!(Bits.convert(12, HexDigits), 3);
+        assert_eq!(Bits.convert(13, HexDigits), 4);
+        assert_eq!(Bits.convert(9, Bytes), 2);
+        assert_eq!(Bytes.convert(2, HexDigits), 4);
+    }
+
     #[test]
     fn test_empty_block_with_known_nonce() {
         let b = Block {
@@ -141,4 +909,500 @@ mod tests {
         let hash = calculate_sha256(s);
         assert!(check_difficulty(&hash, 8))
     }
+
+    #[test]
+    fn test_wire_format() {
+        let data = Data {
+            data: "x".to_string(),
+            nonce: 5,
+        };
+        assert_eq!(serde_json::to_string(&data).unwrap(), r#"["x",5]"#);
+
+        let block = Block {
+            data: Arc::new(vec![
+                data,
+                Data {
+                    data: "y z".to_string(),
+                    nonce: -12,
+                },
+            ]),
+            nonce: Some(45),
+        };
+        assert_eq!(
+            String::from_utf8(serialize_block(&block)).unwrap(),
+            r#"{"data":[["x",5],["y z",-12]],"nonce":45}"#
+        );
+    }
+
+    #[test]
+    fn test_mined_hash_matches_block() {
+        let problem = new_problem(8, vec![], MiniMinerSettings::default());
+
+        let mined = mine(&problem).unwrap();
+        let hash = calculate_sha256(mined.serialized.clone());
+        assert_eq!(mined.hash, crate::encoding::hex_encode(&hash));
+        assert!(check_difficulty(&hash, 8));
+        assert_eq!(
+            mined.serialized,
+            format!(r#"{{"data":[],"nonce":{}}}"#, mined.nonce)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_saved_block_rehashes_to_difficulty() {
+        let dir = std::env::temp_dir().join(format!("mini-miner-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let answer_file = dir.join("answer.json");
+        let mut problem = new_problem(
+            8,
+            vec![Data {
+                data: "4c4a3bd1".to_string(),
+                nonce: 61,
+            }],
+            MiniMinerSettings::default(),
+        );
+        let options = SolveOptions {
+            save_answer: Some(answer_file.clone()),
+            ..Default::default()
+        };
+        MiniMiner::apply_options(&mut problem, &options);
+
+        let answer = MiniMiner::solve(problem).await.unwrap();
+
+        let block_file = block_file_for(&answer_file);
+        assert_eq!(block_file, dir.join("answer.block.json"));
+        let capture: BlockCapture =
+            serde_json::from_str(&std::fs::read_to_string(&block_file).unwrap()).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(capture.block.nonce, Some(answer.nonce));
+        let hash = calculate_sha256(serialize_block(&capture.block));
+        assert_eq!(capture.hash, crate::encoding::hex_encode(&hash));
+        assert!(check_difficulty(&hash, 8));
+    }
+
+    #[test]
+    fn test_search_reports_progress_and_stops_on_cancel() {
+        // unreachable, so only the cancel or max_nonce ends the search
+        let mut problem = new_problem(
+            64,
+            vec![],
+            MiniMinerSettings {
+                threads: Threads::Fixed(2),
+                ..Default::default()
+            },
+        );
+        let progress = Progress::new();
+        let options = SolveOptions {
+            max_nonce: Some(1 << 24),
+            progress: Some(progress.clone()),
+            ..Default::default()
+        };
+        MiniMiner::apply_options(&mut problem, &options);
+
+        let canceller = std::thread::spawn(move || {
+            while progress.position().0 == 0 {
+                std::thread::yield_now();
+            }
+            progress.cancel();
+            progress
+        });
+        let err = mine(&problem).unwrap_err();
+        let progress = canceller.join().unwrap();
+
+        assert!(matches!(
+            HackatticError::from(err),
+            HackatticError::Cancelled
+        ));
+        let (done, total) = progress.position();
+        assert!(done >= SEARCH_CHUNK as u64 && done < 1 << 24, "{done}");
+        assert_eq!(total, Some(expected_hashes(64) as u64));
+
+        // the rate was exported while mining, though no nonce was found
+        let metrics = crate::metrics::global().render();
+        let rate = metrics
+            .lines()
+            .find_map(|line| line.strip_prefix("hackattic_hash_rate "))
+            .unwrap();
+        assert!(rate.parse::<f64>().unwrap() > 0.0, "{metrics}");
+    }
+
+    #[test]
+    fn test_custom_serializer_changes_hash() {
+        let problem = new_problem(
+            8,
+            vec![Data {
+                data: "4c4a3bd1".to_string(),
+                nonce: 61,
+            }],
+            MiniMinerSettings::default(),
+        );
+        let pretty = |block: &Block| serde_json::to_vec_pretty(block).unwrap();
+
+        let mined = mine_with(&problem, pretty).unwrap();
+        let block = problem.block.with_nonce(mined.nonce);
+        assert_eq!(mined.serialized.as_bytes(), pretty(&block));
+        assert!(mined.serialized.contains('\n'));
+
+        let hash = calculate_sha256(pretty(&block));
+        assert_eq!(mined.hash, crate::encoding::hex_encode(&hash));
+        assert!(check_difficulty(&hash, 8));
+        assert_ne!(hash, calculate_sha256(serialize_block(&block)));
+
+        // the default is unchanged
+        assert_eq!(
+            serialize_block(&block),
+            format!(r#"{{"data":[["4c4a3bd1",61]],"nonce":{}}}"#, mined.nonce).as_bytes()
+        );
+    }
+
+    #[test]
+    fn test_configured_serialization_changes_hash() {
+        let config = Config::parse("[challenges.mini_miner]\nserialization = \"pretty\"").unwrap();
+        let context = test_util::context("", config);
+        let mut problem = new_problem(
+            8,
+            vec![Data {
+                data: "4c4a3bd1".to_string(),
+                nonce: 61,
+            }],
+            MiniMinerSettings::default(),
+        );
+        MiniMiner::configure(&mut problem, &context).unwrap();
+        assert_eq!(problem.settings.serialization, Serialization::Pretty);
+
+        let mined = mine(&problem).unwrap();
+        let block = problem.block.with_nonce(mined.nonce);
+        let pretty = calculate_sha256(Serialization::Pretty.serialize(&block));
+        let compact = calculate_sha256(Serialization::Compact.serialize(&block));
+        assert_eq!(mined.hash, crate::encoding::hex_encode(&pretty));
+        assert_ne!(pretty, compact);
+
+        let unset = Config::default().challenge::<MiniMinerConfig>().unwrap();
+        assert_eq!(unset.serialization, Serialization::Compact);
+    }
+
+    #[test]
+    fn test_configured_digest_changes_nonce() {
+        let config = Config::parse("[challenges.mini_miner]\ndigest = \"sha512\"").unwrap();
+        let context = test_util::context("", config);
+        let mut problem = new_problem(
+            8,
+            vec![Data {
+                data: "4c4a3bd1".to_string(),
+                nonce: 61,
+            }],
+            MiniMinerSettings::default(),
+        );
+        let sha256 = mine(&problem).unwrap();
+        MiniMiner::configure(&mut problem, &context).unwrap();
+        assert_eq!(problem.settings.digest, BlockDigest::Sha512);
+        let sha512 = mine(&problem).unwrap();
+
+        // both searches return the lowest passing nonce
+        assert_ne!(sha256.nonce, sha512.nonce);
+        let block = serialize_block(&problem.block.with_nonce(sha512.nonce));
+        let hash = Sha512::digest(&block);
+        assert_eq!(hash.len(), 64);
+        assert!(check_difficulty(&hash, 8));
+        assert_eq!(sha512.hash, crate::encoding::hex_encode(&hash));
+
+        let unset = Config::default().challenge::<MiniMinerConfig>().unwrap();
+        assert_eq!(unset.digest, BlockDigest::Sha256);
+    }
+
+    async fn submitted_nonce(playground: bool) -> String {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/challenges/mini_miner/problem/"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"{"difficulty": 12, "block": {"data": [["4c4a3bd1", 61]], "nonce": null}}"#,
+            ))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/challenges/mini_miner/solve/"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("{}"))
+            .mount(&server)
+            .await;
+
+        let context = HackatticContext {
+            playground,
+            ..test_util::context(&server.uri(), Config::default())
+        };
+        let options = SolveOptions {
+            difficulty_override: Some(0),
+            ..Default::default()
+        };
+        solve::<MiniMiner>(reqwest::Client::new(), &context, &options)
+            .await
+            .unwrap();
+
+        let requests = server.received_requests().await.unwrap();
+        String::from_utf8(requests.last().unwrap().body.clone()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_difficulty_override_only_in_playground() {
+        // difficulty 0 accepts the very first nonce tried
+        assert_eq!(submitted_nonce(true).await, r#"{"nonce":0}"#);
+        assert_ne!(submitted_nonce(false).await, r#"{"nonce":0}"#);
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_difficulty_is_not_found() {
+        let problem = new_problem(257, vec![], MiniMinerSettings::default());
+
+        let err = MiniMiner::solve(problem).await.unwrap_err();
+        assert!(matches!(
+            HackatticError::from(err),
+            HackatticError::NotFound(_)
+        ));
+    }
+
+    #[test]
+    fn test_difficulty_shortfall() {
+        assert_eq!(difficulty_shortfall(&[0, 0, 0, 0xFF], 24), Ok(()));
+        assert_eq!(difficulty_shortfall(&[0, 0, 0, 0xFF], 25), Err(1));
+        assert_eq!(difficulty_shortfall(&[0, 0, 0x0F, 0xFF], 22), Err(2));
+        assert_eq!(difficulty_shortfall(&[0x80, 0], 8), Err(8));
+        assert_eq!(difficulty_shortfall(&[0, 0], 20), Err(4));
+
+        for d in 0..=32 {
+            let hash = [0, 0x01, 0xFF, 0xFF];
+            assert_eq!(
+                difficulty_shortfall(&hash, d).is_ok(),
+                check_difficulty(&hash, d),
+                "difficulty {d}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_scaler_adds_threads_after_window() {
+        let policy = ScalingPolicy {
+            start_threads: 2,
+            max_threads: 6,
+            window_secs: 1.0,
+        };
+        let start = Instant::now();
+        let mut scaler = ThreadScaler::new(policy, start);
+        assert_eq!(scaler.threads(), 2);
+
+        assert!(!scaler.tick(start + Duration::from_millis(500)));
+        assert_eq!(scaler.threads(), 2);
+
+        assert!(scaler.tick(start + Duration::from_millis(1000)));
+        assert_eq!(scaler.threads(), 4);
+
+        // the window restarts after scaling
+        assert!(!scaler.tick(start + Duration::from_millis(1500)));
+        assert!(scaler.tick(start + Duration::from_millis(2000)));
+        assert_eq!(scaler.threads(), 6);
+        assert!(!scaler.tick(start + Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn test_invalid_scaling_window_is_rejected() {
+        for window in ["-1.0", "nan", "1e300"] {
+            let config = Config::parse(&format!(
+                "[challenges.mini_miner.scaling]\nwindow_secs = {window}"
+            ))
+            .unwrap();
+            let context = test_util::context("", config);
+            let mut problem: MiniMinerProblem =
+                serde_json::from_str(r#"{"difficulty": 8, "block": {"data": [], "nonce": null}}"#)
+                    .unwrap();
+            let err = MiniMiner::configure(&mut problem, &context).unwrap_err();
+            assert!(err.to_string().contains("window_secs"), "{window}: {err}");
+        }
+
+        // built in code instead, it just never scales
+        let policy = ScalingPolicy {
+            start_threads: 1,
+            max_threads: 4,
+            window_secs: f64::NAN,
+        };
+        let start = Instant::now();
+        let mut scaler = ThreadScaler::new(policy, start);
+        assert!(!scaler.tick(start + Duration::from_secs(3600)));
+    }
+
+    #[test]
+    fn test_adaptive_search_matches_fixed() {
+        let problem = |threads| {
+            new_problem(
+                18,
+                vec![],
+                MiniMinerSettings {
+                    threads,
+                    ..Default::default()
+                },
+            )
+        };
+        let policy = ScalingPolicy {
+            start_threads: 1,
+            max_threads: 4,
+            window_secs: 0.0,
+        };
+
+        let fixed = mine(&problem(Threads::Fixed(2))).unwrap();
+        let adaptive = mine(&problem(Threads::Adaptive(policy))).unwrap();
+        assert_eq!(fixed.nonce, adaptive.nonce);
+    }
+
+    #[test]
+    fn test_search_iterator_yields_valid_nonce() {
+        let problem = |difficulty, max_nonce| {
+            new_problem(
+                difficulty,
+                vec![],
+                MiniMinerSettings {
+                    max_nonce,
+                    ..Default::default()
+                },
+            )
+        };
+
+        let easy = problem(8, None);
+        let mined = MiniMinerSearch::new(&easy)
+            .unwrap()
+            .find_map(|step| match step {
+                SearchStep::Found(mined) => Some(mined),
+                SearchStep::Miss { .. } => None,
+            })
+            .unwrap();
+        let hash = calculate_sha256(&mined.serialized);
+        assert!(check_difficulty(&hash, 8));
+        assert_eq!(mined.nonce, mine(&easy).unwrap().nonce);
+
+        // steps cover the nonces in order and the search ends at max_nonce
+        let hard = problem(64, Some(2 * SEARCH_CHUNK + 5));
+        let searched: Vec<_> = MiniMinerSearch::new(&hard)
+            .unwrap()
+            .map(|step| match step {
+                SearchStep::Miss { searched, .. } => searched,
+                SearchStep::Found(mined) => panic!("{mined:?} can't pass"),
+            })
+            .collect();
+        assert_eq!(
+            searched,
+            [
+                0..=SEARCH_CHUNK - 1,
+                SEARCH_CHUNK..=2 * SEARCH_CHUNK - 1,
+                2 * SEARCH_CHUNK..=2 * SEARCH_CHUNK + 5
+            ]
+        );
+    }
+
+    #[test]
+    fn test_search_stays_within_non_negative_nonces() {
+        let problem = new_problem(16, vec![], MiniMinerSettings::default());
+        let passes_at = |nonce: i32| {
+            let end = format!("\"nonce\":{nonce}}}");
+            move |bytes: &[u8]| match bytes.ends_with(end.as_bytes()) {
+                true => [0u8; 4],
+                false => [0xff; 4],
+            }
+        };
+        let found = |search: MiniMinerSearch<_, _, _>| {
+            search
+                .filter_map(|step| match step {
+                    SearchStep::Found(mined) => Some(mined.nonce),
+                    SearchStep::Miss { .. } => None,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // the last nonce is reached without overflowing, and the search ends there
+        let search = MiniMinerSearch::with_hasher(&problem, serialize_block, passes_at(i32::MAX))
+            .unwrap()
+            .starting_at(i32::MAX - 10);
+        assert_eq!(found(search), [i32::MAX]);
+
+        // a nonce only a negative start could reach is never tried
+        let mut problem = problem;
+        problem.settings.max_nonce = Some(10);
+        let search = MiniMinerSearch::with_hasher(&problem, serialize_block, passes_at(-1))
+            .unwrap()
+            .starting_at(-5);
+        assert!(found(search).is_empty());
+    }
+
+    #[test]
+    fn test_exhausted_nonces_report_best() {
+        assert_eq!(leading_zero_bits(&[0, 0x10, 0xff]), 11);
+        assert_eq!(leading_zero_bits(&[0, 0]), 16);
+
+        let problem = new_problem(
+            64,
+            vec![],
+            MiniMinerSettings {
+                max_nonce: Some(15),
+                threads: Threads::Fixed(2),
+                ..Default::default()
+            },
+        );
+
+        let err = mine(&problem).unwrap_err();
+        let message = err.to_string();
+        assert!(
+            message.contains("no nonce in 0..=15 has 64 leading zero bits, the best had "),
+            "{message}"
+        );
+        let best: u32 = message.rsplit(' ').next().unwrap().parse().unwrap();
+        assert!(best < 64, "{message}");
+    }
+
+    #[test]
+    fn test_probe_measures_hash_rate() {
+        let block = Block {
+            data: Arc::new(vec![]),
+            nonce: None,
+        };
+        let rate = probe_hash_rate(&block, serialize_block, digest::<Sha256>);
+        assert!(rate.is_finite() && rate > 0.0, "{rate}");
+
+        assert_eq!(expected_hashes(8), 256.0);
+        let estimate = estimate_work(&new_problem(
+            40,
+            vec![],
+            MiniMinerSettings {
+                threads: Threads::Fixed(1),
+                ..Default::default()
+            },
+        ))
+        .unwrap();
+        assert!(estimate.eta > Duration::from_secs(1), "{estimate:?}");
+    }
+
+    #[test]
+    fn test_stub_hash_picks_known_nonce() {
+        let problem = new_problem(
+            16,
+            vec![],
+            MiniMinerSettings {
+                max_nonce: Some(1000),
+                threads: Threads::Fixed(2),
+                ..Default::default()
+            },
+        );
+        // all zeros only for nonce 321, every other block misses by a mile
+        let stub = |bytes: &[u8]| match bytes.ends_with(b"\"nonce\":321}") {
+            true => [0u8; 4],
+            false => [0xff; 4],
+        };
+
+        let mined = mine_with_hasher(&problem, serialize_block, stub).unwrap();
+        assert_eq!(mined.nonce, 321);
+        assert_eq!(mined.hash, "00000000");
+
+        // the stub's 32 bits can't have 40 leading zeros
+        let problem = MiniMinerProblem {
+            difficulty: 40,
+            ..problem
+        };
+        let err = mine_with_hasher(&problem, serialize_block, stub).unwrap_err();
+        assert!(err.to_string().contains("no nonce can satisfy"), "{err}");
+    }
 }
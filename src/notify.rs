@@ -0,0 +1,94 @@
+use std::time::Duration;
+
+use reqwest::Client;
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::HackatticError;
+
+/// Summary posted to `--notify-url` once a solve finishes.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Notification {
+    pub challenge: String,
+    pub accepted: bool,
+    pub duration_secs: f64,
+    /// The server's response if accepted, the error otherwise.
+    pub detail: String,
+}
+
+impl Notification {
+    pub fn new(
+        challenge: &str,
+        outcome: &Result<String, HackatticError>,
+        elapsed: Duration,
+    ) -> Self {
+        Notification {
+            challenge: challenge.to_string(),
+            accepted: outcome.is_ok(),
+            duration_secs: elapsed.as_secs_f64(),
+            detail: match outcome {
+                Ok(response) => response.clone(),
+                Err(e) => e.to_string(),
+            },
+        }
+    }
+}
+
+/// Posts `notification` to the webhook. A webhook that can't be reached or
+/// refuses it is only logged, the run's own outcome stands.
+pub async fn notify(client: &Client, url: &str, notification: &Notification) {
+    let result = client
+        .post(url)
+        .json(notification)
+        .send()
+        .await
+        .and_then(|resp| resp.error_for_status());
+    match result {
+        Ok(_) => info!("notified {url} about {}", notification.challenge),
+        Err(e) => warn!("failed to notify {url}: {e}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::json;
+    use wiremock::{
+        matchers::{body_json, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::{notify, Notification};
+    use crate::HackatticError;
+
+    #[tokio::test]
+    async fn test_webhook_receives_summary() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/hook"))
+            .and(body_json(json!({
+                "challenge": "mini_miner",
+                "accepted": false,
+                "duration_secs": 1.5,
+                "detail": "answer rejected: wrong nonce",
+            })))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let outcome = Err(HackatticError::Rejected("wrong nonce".to_string()));
+        let notification = Notification::new("mini_miner", &outcome, Duration::from_millis(1500));
+        notify(
+            &reqwest::Client::new(),
+            &format!("{}/hook", server.uri()),
+            &notification,
+        )
+        .await;
+
+        // an unreachable webhook is only logged
+        let unreachable = format!("{}/missing", server.uri());
+        notify(&reqwest::Client::new(), &unreachable, &notification).await;
+    }
+}
@@ -1,25 +1,35 @@
-use super::Hackattic;
-use anyhow::Result;
-use base16::encode_lower;
+use super::{
+    encoding::{hex_decode, hex_encode},
+    progress::Progress,
+    secret::Zeroizing,
+    About, Hackattic, SolveOptions,
+};
+use anyhow::{bail, Result};
 use base64::{engine::general_purpose, Engine};
 use hmac::{
     digest::{generic_array::GenericArray, typenum},
     Hmac, Mac,
 };
+use rayon::prelude::*;
 use scrypt::{scrypt, Params};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use tracing::debug;
+use std::time::Instant;
+use tracing::{debug, debug_span, warn};
 
 type HmacSha256 = Hmac<Sha256>;
 type U8_32 = GenericArray<u8, typenum::U32>;
 
+/// `{"password", "salt", "pbkdf2": {"hash", "rounds"}, "scrypt": {"N", "p", "r", ...}}`
 #[derive(Deserialize, Debug)]
 pub struct PasswordHashingProblem {
-    pub password: String,
-    pub salt: String,
+    pub password: Zeroizing<String>,
+    pub salt: Zeroizing<String>,
     pub pbkdf2: PBKDF2,
     pub scrypt: ScryptParameters,
+    /// One unit per hash, checked for a cancel before each.
+    #[serde(skip)]
+    pub progress: Progress,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -41,7 +51,7 @@ pub struct ScryptParameters {
     pub control: String,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 #[serde(rename = "")]
 pub struct PasswordHashingAnswer {
     pub sha256: String,
@@ -54,36 +64,92 @@ pub struct PasswordHashing;
 
 impl Hackattic for PasswordHashing {
     const NAME: &'static str = "password_hashing";
+    const ABOUT: About = About {
+        summary: "Hash the password with SHA-256, HMAC-SHA256, PBKDF2-SHA256 and scrypt.",
+        problem: r#"{"password", "salt", "pbkdf2": {"hash", "rounds"}, "scrypt": {"N", "p", "r", "buflen", "_control"}}"#,
+        prerequisites: &[],
+    };
     type Problem = PasswordHashingProblem;
     type Answer = PasswordHashingAnswer;
 
+    fn answer_schema() -> Option<serde_json::Value> {
+        let hex = serde_json::json!({"type": "string", "pattern": "^[0-9a-f]+$"});
+        Some(serde_json::json!({
+            "type": "object",
+            "required": ["sha256", "hmac", "pbkdf2", "scrypt"],
+            "properties": {"sha256": hex, "hmac": hex, "pbkdf2": hex, "scrypt": hex}
+        }))
+    }
+
+    fn apply_options(problem: &mut Self::Problem, options: &SolveOptions) {
+        problem.progress = options.progress.clone().unwrap_or_default();
+    }
+
     async fn solve(problem: Self::Problem) -> Result<Self::Answer> {
-        debug!("{:?}", problem);
-        let sha256 = encode_lower(&calculate_sha256(&problem.password));
-        debug!(sha256);
+        hash_password(problem)
+    }
+}
 
-        let key = general_purpose::STANDARD.decode(&problem.salt)?;
-        let hmac = encode_lower(&calculate_hmac(problem.password.as_bytes(), &key));
+fn hash_password(problem: PasswordHashingProblem) -> Result<PasswordHashingAnswer> {
+    let progress = &problem.progress;
+    progress.set_total(HASHES);
+    let sha256 = timed(progress, "sha256", || {
+        hex_encode(&calculate_sha256(&problem.password))
+    })?;
+    debug!(sha256);
 
-        debug!(hmac);
+    let key = Zeroizing::new(general_purpose::STANDARD.decode(&*problem.salt)?);
+    let hmac = timed(progress, "hmac", || {
+        hex_encode(&calculate_hmac(problem.password.as_bytes(), &key))
+    })?;
 
-        let pbkdf2 = encode_lower(&compute_pbkdf2(
-            &problem.password,
+    debug!(hmac);
+
+    let pbkdf2 = timed(progress, "pbkdf2", || {
+        let mut pbkdf2 = Zeroizing::new([0; PBKDF2_BLOCK_LEN]);
+        pbkdf2_sha256(
+            problem.password.as_bytes(),
             &key,
             problem.pbkdf2.rounds,
-        ));
-        debug!(pbkdf2);
+            &mut *pbkdf2,
+        );
+        hex_encode(&*pbkdf2)
+    })?;
+    debug!(pbkdf2);
+
+    let scrypt = timed(progress, "scrypt", || {
+        calculate_scrypt(
+            &problem.password,
+            &key,
+            &problem.scrypt,
+            &ScryptLimits::from_env(),
+        )
+    })??;
+    check_control(&problem.scrypt, &scrypt);
+    let scrypt = hex_encode(&scrypt);
+    debug!(scrypt);
 
-        let scrypt = encode_lower(&(calculate_scrypt(&problem.password, &key, problem.scrypt)?));
-        debug!(scrypt);
+    Ok(PasswordHashingAnswer {
+        sha256,
+        hmac,
+        pbkdf2,
+        scrypt,
+    })
+}
 
-        Ok(PasswordHashingAnswer {
-            sha256,
-            hmac,
-            pbkdf2,
-            scrypt,
-        })
-    }
+/// sha256, hmac, pbkdf2 and scrypt.
+const HASHES: u64 = 4;
+
+/// Runs one of the hashes in a span of its own and logs how long it took,
+/// scrypt usually dwarfing the rest.
+fn timed<R>(progress: &Progress, step: &str, f: impl FnOnce() -> R) -> Result<R> {
+    progress.check()?;
+    let _span = debug_span!("hash", step).entered();
+    let started = Instant::now();
+    let result = f();
+    debug!(elapsed = ?started.elapsed(), "{step} done");
+    progress.advance(1);
+    Ok(result)
 }
 
 fn calculate_sha256(s: &str) -> U8_32 {
@@ -98,29 +164,133 @@ fn calculate_hmac(s: &[u8], k: &[u8]) -> U8_32 {
     mac.finalize().into_bytes()
 }
 
-fn compute_pbkdf2(s: &str, salt: &[u8], rounds: u32) -> U8_32 {
-    let mut key = salt.to_owned();
-    key.append(&mut vec![0u8, 0u8, 0u8, 1u8]);
+/// Bytes of PBKDF2-HMAC-SHA256 output each block of the derivation yields.
+pub const PBKDF2_BLOCK_LEN: usize = 32;
+
+/// PBKDF2-HMAC-SHA256 filling `out`, one output block after another.
+pub fn pbkdf2_sha256(password: &[u8], salt: &[u8], rounds: u32, out: &mut [u8]) {
+    pbkdf2::pbkdf2_hmac::<Sha256>(password, salt, rounds, out);
+}
+
+/// `pbkdf2_sha256` with the output blocks derived in parallel. Each block's
+/// chain of rounds is sequential, so this only pays off when `out` spans
+/// several blocks.
+pub fn pbkdf2_sha256_parallel(password: &[u8], salt: &[u8], rounds: u32, out: &mut [u8]) {
+    let prf = HmacSha256::new_from_slice(password).expect("HMAC takes keys of any length");
+    out.par_chunks_mut(PBKDF2_BLOCK_LEN)
+        .enumerate()
+        .for_each(|(i, block)| pbkdf2_block(&prf, salt, rounds, i as u32 + 1, block));
+}
+
+/// RFC 2898's INT(i), the block index salted into each block's first round.
+fn int(index: u32) -> [u8; 4] {
+    index.to_be_bytes()
+}
 
-    let mut u_prev = calculate_hmac(&key, s.as_bytes());
-    let mut dk = u_prev;
+/// Block `index` (1 based) of the derivation, `prf` already keyed with the
+/// password so each round only clones it.
+fn pbkdf2_block(prf: &HmacSha256, salt: &[u8], rounds: u32, index: u32, block: &mut [u8]) {
+    let mut mac = prf.clone();
+    mac.update(salt);
+    mac.update(&int(index));
+    let mut u = mac.finalize().into_bytes();
+    let mut dk = u;
 
     for _ in 1..rounds {
-        let u_cur = calculate_hmac(&u_prev, s.as_bytes());
-        xor_update(&mut dk, u_cur);
-        u_prev = u_cur;
+        let mut mac = prf.clone();
+        mac.update(&u);
+        u = mac.finalize().into_bytes();
+        dk.iter_mut().zip(&u).for_each(|(d, u)| *d ^= u);
+    }
+
+    block.copy_from_slice(&dk[..block.len()]);
+}
+
+/// Upper bounds on the server supplied scrypt parameters, so a malformed
+/// problem can't make us allocate unbounded memory.
+pub struct ScryptLimits {
+    pub max_buflen: usize,
+    pub max_memory: u64,
+}
+
+impl Default for ScryptLimits {
+    fn default() -> Self {
+        ScryptLimits {
+            max_buflen: 1 << 20,
+            max_memory: 1 << 30,
+        }
+    }
+}
+
+impl ScryptLimits {
+    /// Defaults, overridable with `HA_SCRYPT_MAX_BUFLEN` and `HA_SCRYPT_MAX_MEMORY`.
+    pub fn from_env() -> Self {
+        let default = ScryptLimits::default();
+        ScryptLimits {
+            max_buflen: env_or("HA_SCRYPT_MAX_BUFLEN", default.max_buflen),
+            max_memory: env_or("HA_SCRYPT_MAX_MEMORY", default.max_memory),
+        }
     }
 
-    dk
+    fn check(&self, parameters: &ScryptParameters) -> Result<()> {
+        if parameters.buflen > self.max_buflen {
+            bail!(
+                "scrypt buflen {} exceeds limit of {} bytes",
+                parameters.buflen,
+                self.max_buflen
+            );
+        }
+
+        // scrypt's working set is 128 * r * N for V plus 128 * r * p for B
+        let memory = 128
+            * u64::from(parameters.block_size)
+            * (u64::from(parameters.n) + u64::from(parameters.parallization));
+        if memory > self.max_memory {
+            bail!(
+                "scrypt parameters N={} r={} p={} need {} bytes, exceeding limit of {} bytes",
+                parameters.n,
+                parameters.block_size,
+                parameters.parallization,
+                memory,
+                self.max_memory
+            );
+        }
+        Ok(())
+    }
+}
+
+fn env_or<T: std::str::FromStr>(key: &str, default: T) -> T {
+    std::env::var(key)
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(default)
 }
 
-fn xor_update(a: &mut U8_32, b: U8_32) {
-    for i in 0..32 {
-        a[i] ^= b[i];
+/// `_control` is the hex scrypt output the server expects for the
+/// problem's password, salt and parameters, so a mismatch with ours means
+/// the scrypt here, or how the parameters were read, is off. Only warned
+/// about, the server has the final say.
+fn check_control(parameters: &ScryptParameters, output: &[u8]) -> bool {
+    debug!(control = %parameters.control, "scrypt control");
+    let matches = hex_decode(&parameters.control).is_ok_and(|control| control == output);
+    if !matches {
+        warn!(
+            "scrypt _control {:?} differs from our output {}; the answer may be wrong",
+            parameters.control,
+            hex_encode(output)
+        );
     }
+    matches
 }
 
-fn calculate_scrypt(s: &str, salt: &[u8], parameters: ScryptParameters) -> Result<Vec<u8>> {
+fn calculate_scrypt(
+    s: &str,
+    salt: &[u8],
+    parameters: &ScryptParameters,
+    limits: &ScryptLimits,
+) -> Result<Zeroizing<Vec<u8>>> {
+    limits.check(parameters)?;
+
     let n_lg = f32::log2(parameters.n as f32) as u8;
     let params = Params::new(
         n_lg,
@@ -129,11 +299,183 @@ fn calculate_scrypt(s: &str, salt: &[u8], parameters: ScryptParameters) -> Resul
         parameters.buflen,
     )?;
 
-    let mut output = vec![0u8; parameters.buflen];
+    let mut output = Zeroizing::new(vec![0u8; parameters.buflen]);
     scrypt(s.as_bytes(), salt, &params, &mut output)?;
 
     Ok(output)
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::{
+        calculate_scrypt, check_control, hash_password, int, pbkdf2_block, pbkdf2_sha256,
+        pbkdf2_sha256_parallel, HmacSha256, PasswordHashing, PasswordHashingProblem, ScryptLimits,
+        ScryptParameters, PBKDF2, PBKDF2_BLOCK_LEN,
+    };
+    use crate::{
+        encoding::{hex_decode, hex_encode},
+        progress::Progress,
+        test_util::capture_logs,
+        Hackattic, HackatticError,
+    };
+    use hmac::Mac;
+
+    fn scrypt_parameters(n: u32, block_size: u32, buflen: usize) -> ScryptParameters {
+        ScryptParameters {
+            n,
+            parallization: 1,
+            block_size,
+            buflen,
+            control: String::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_invalid_salt_is_solver_failure() {
+        let problem = PasswordHashingProblem {
+            password: "rosebud".to_string().into(),
+            salt: "not base64!".to_string().into(),
+            pbkdf2: PBKDF2 {
+                hash: "sha256".to_string(),
+                rounds: 1,
+            },
+            scrypt: scrypt_parameters(2, 1, 32),
+            progress: Progress::default(),
+        };
+
+        let err = PasswordHashing::solve(problem).await.unwrap_err();
+        assert!(matches!(
+            HackatticError::from(err),
+            HackatticError::SolverFailed(_)
+        ));
+    }
+
+    #[test]
+    fn test_each_hash_is_timed() {
+        let problem = PasswordHashingProblem {
+            password: "rosebud".to_string().into(),
+            salt: "c2FsdA==".to_string().into(),
+            pbkdf2: PBKDF2 {
+                hash: "sha256".to_string(),
+                rounds: 2,
+            },
+            scrypt: scrypt_parameters(2, 1, 32),
+            progress: Progress::default(),
+        };
+
+        let (answer, logs) = capture_logs(|| hash_password(problem));
+        answer.unwrap();
+        for step in ["sha256", "hmac", "pbkdf2", "scrypt"] {
+            assert!(
+                logs.contains(&format!("hash{{step=\"{step}\"}}: ")),
+                "no {step} span in {logs}"
+            );
+            assert!(logs.contains(&format!("{step} done elapsed=")), "{logs}");
+        }
+    }
+
+    #[test]
+    fn test_pbkdf2_rfc_vectors() {
+        // RFC 7914 section 11, both spanning two output blocks
+        let vectors: [(&str, &str, u32, &str); 2] = [
+            (
+                "passwd",
+                "salt",
+                1,
+                "55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc\
+                 49ca9cccf179b645991664b39d77ef317c71b845b1e30bd509112041d3a19783",
+            ),
+            (
+                "Password",
+                "NaCl",
+                80000,
+                "4ddcd8f60b98be21830cee5ef22701f9641a4418d04c0414aeff08876b34ab56\
+                 a1d425a1225833549adb841b51c9b3176a272bdebba1d078478f62b397f33c8d",
+            ),
+        ];
+
+        for (password, salt, rounds, expected) in vectors {
+            let (password, salt) = (password.as_bytes(), salt.as_bytes());
+            let expected = hex_decode(expected).unwrap();
+
+            // 70 bytes ends in a partial third block
+            let mut sequential = vec![0; 70];
+            pbkdf2_sha256(password, salt, rounds, &mut sequential);
+            assert_eq!(hex_encode(&sequential[..64]), hex_encode(&expected));
+
+            let mut parallel = vec![0; 70];
+            pbkdf2_sha256_parallel(password, salt, rounds, &mut parallel);
+            assert_eq!(parallel, sequential);
+        }
+    }
+
+    #[test]
+    fn test_pbkdf2_blocks_use_their_index() {
+        assert_eq!(int(1), [0, 0, 0, 1]);
+        assert_eq!(int(2), [0, 0, 0, 2]);
+        assert_eq!(int(0x0102_0304), [1, 2, 3, 4]);
+
+        // RFC 7914 section 11, "passwd" / "salt" / 1 round, one block each
+        let expected = [
+            "55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc",
+            "49ca9cccf179b645991664b39d77ef317c71b845b1e30bd509112041d3a19783",
+        ];
+        let prf = HmacSha256::new_from_slice(b"passwd").unwrap();
+        for (index, expected) in (1..).zip(expected) {
+            let mut block = [0; PBKDF2_BLOCK_LEN];
+            pbkdf2_block(&prf, b"salt", 1, index, &mut block);
+            assert_eq!(hex_encode(&block), expected, "block {index}");
+        }
+    }
+
+    #[test]
+    fn test_absurd_buflen_is_rejected() {
+        let err = calculate_scrypt(
+            "rosebud",
+            b"salt",
+            &scrypt_parameters(2, 1, usize::MAX),
+            &ScryptLimits::default(),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("buflen"), "{err}");
+    }
+
+    #[test]
+    fn test_excessive_memory_is_rejected() {
+        let err = calculate_scrypt(
+            "rosebud",
+            b"salt",
+            &scrypt_parameters(1 << 30, 8, 32),
+            &ScryptLimits::default(),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("N=1073741824 r=8"), "{err}");
+    }
+
+    #[test]
+    fn test_control_is_checked_against_scrypt() {
+        // RFC 7914's first test vector, the empty password and salt
+        let control = "77d6576238657b203b19ca42c18a0497f16b4844e3074ae8dfdffa3fede21442\
+                       fcd0069ded0948f8326a753a0fc81f17e8d3e0fb2e0d3628cf35e20c38d18906";
+        let parameters: ScryptParameters = serde_json::from_value(serde_json::json!({
+            "N": 16, "p": 1, "r": 1, "buflen": 64, "_control": control
+        }))
+        .unwrap();
+        assert_eq!(parameters.control, control);
+        let output = calculate_scrypt("", b"", &parameters, &ScryptLimits::default()).unwrap();
+
+        let (matches, logs) = capture_logs(|| check_control(&parameters, &output));
+        assert!(matches);
+        assert!(logs.contains(&format!("control={control}")), "{logs}");
+        assert!(!logs.contains("WARN"), "{logs}");
+
+        let other = calculate_scrypt("x", b"", &parameters, &ScryptLimits::default()).unwrap();
+        let (matches, logs) = capture_logs(|| check_control(&parameters, &other));
+        assert!(!matches);
+        assert!(logs.contains("differs from our output"), "{logs}");
+
+        let mut parameters = parameters;
+        parameters.control = "use-sha512".to_string();
+        assert!(!capture_logs(|| check_control(&parameters, &output)).0);
+    }
+}
@@ -0,0 +1,100 @@
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio_postgres::tls::{self, ChannelBinding, MakeTlsConnect, TlsConnect};
+
+/// TLS for tokio-postgres through native-tls, the same stack reqwest uses,
+/// for servers that refuse plain connections.
+#[derive(Clone)]
+pub struct MakeNativeTlsConnector(native_tls::TlsConnector);
+
+impl MakeNativeTlsConnector {
+    pub fn new(connector: native_tls::TlsConnector) -> Self {
+        MakeNativeTlsConnector(connector)
+    }
+}
+
+impl<S> MakeTlsConnect<S> for MakeNativeTlsConnector
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    type Stream = NativeTlsStream<S>;
+    type TlsConnect = NativeTlsConnect;
+    type Error = native_tls::Error;
+
+    fn make_tls_connect(&mut self, domain: &str) -> Result<NativeTlsConnect, native_tls::Error> {
+        Ok(NativeTlsConnect {
+            connector: self.0.clone().into(),
+            domain: domain.to_string(),
+        })
+    }
+}
+
+/// Connects one stream to `domain`, its name checked against the server's
+/// certificate.
+pub struct NativeTlsConnect {
+    connector: tokio_native_tls::TlsConnector,
+    domain: String,
+}
+
+impl<S> TlsConnect<S> for NativeTlsConnect
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    type Stream = NativeTlsStream<S>;
+    type Error = native_tls::Error;
+    type Future =
+        Pin<Box<dyn Future<Output = Result<NativeTlsStream<S>, native_tls::Error>> + Send>>;
+
+    fn connect(self, stream: S) -> Self::Future {
+        Box::pin(async move {
+            let stream = self.connector.connect(&self.domain, stream).await?;
+            Ok(NativeTlsStream(stream))
+        })
+    }
+}
+
+pub struct NativeTlsStream<S>(tokio_native_tls::TlsStream<S>);
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncRead for NativeTlsStream<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_read(cx, buf)
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncWrite for NativeTlsStream<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_shutdown(cx)
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> tls::TlsStream for NativeTlsStream<S> {
+    // lets SCRAM bind the login to this connection's certificate
+    fn channel_binding(&self) -> ChannelBinding {
+        match self.0.get_ref().tls_server_end_point() {
+            Ok(Some(end_point)) => ChannelBinding::tls_server_end_point(end_point),
+            _ => ChannelBinding::none(),
+        }
+    }
+}
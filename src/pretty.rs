@@ -0,0 +1,118 @@
+use serde_json::Value;
+
+/// Strings longer than this are cut down, e.g. backup_restore's dump.
+const MAX_STRING_LEN: usize = 80;
+/// How much of a cut string is kept.
+const KEPT_LEN: usize = 60;
+
+const KEY: &str = "34";
+const STRING: &str = "32";
+const LITERAL: &str = "33";
+const NOTE: &str = "2";
+
+/// Indents `value` for reading, truncating long strings with a note of
+/// their length. `color` adds ANSI colors, meant for a terminal. The output
+/// isn't valid JSON once something was cut.
+pub fn pretty(value: &Value, color: bool) -> String {
+    let mut out = String::new();
+    write_value(&mut out, value, 0, color);
+    out
+}
+
+fn write_value(out: &mut String, value: &Value, indent: usize, color: bool) {
+    match value {
+        Value::String(s) => write_string(out, s, color),
+        Value::Array(items) if !items.is_empty() => {
+            out.push('[');
+            for (index, item) in items.iter().enumerate() {
+                separate(out, index, indent + 1);
+                write_value(out, item, indent + 1, color);
+            }
+            close(out, indent, ']');
+        }
+        Value::Object(fields) if !fields.is_empty() => {
+            out.push('{');
+            for (index, (key, item)) in fields.iter().enumerate() {
+                separate(out, index, indent + 1);
+                paint(out, &Value::from(key.as_str()).to_string(), KEY, color);
+                out.push_str(": ");
+                write_value(out, item, indent + 1, color);
+            }
+            close(out, indent, '}');
+        }
+        other => paint(out, &other.to_string(), LITERAL, color),
+    }
+}
+
+fn write_string(out: &mut String, s: &str, color: bool) {
+    if s.len() <= MAX_STRING_LEN {
+        paint(out, &Value::from(s).to_string(), STRING, color);
+        return;
+    }
+    let mut end = KEPT_LEN;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    let kept = Value::from(&s[..end]).to_string();
+    // the ellipsis goes inside the closing quote
+    paint(
+        out,
+        &format!("{}...\"", &kept[..kept.len() - 1]),
+        STRING,
+        color,
+    );
+    paint(out, &format!(" ({} bytes)", s.len()), NOTE, color);
+}
+
+fn separate(out: &mut String, index: usize, indent: usize) {
+    if index > 0 {
+        out.push(',');
+    }
+    out.push('\n');
+    out.push_str(&"  ".repeat(indent));
+}
+
+fn close(out: &mut String, indent: usize, bracket: char) {
+    out.push('\n');
+    out.push_str(&"  ".repeat(indent));
+    out.push(bracket);
+}
+
+fn paint(out: &mut String, text: &str, code: &str, color: bool) {
+    if color {
+        out.push_str(&format!("\x1b[{code}m{text}\x1b[0m"));
+    } else {
+        out.push_str(text);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::pretty;
+
+    #[test]
+    fn test_long_string_is_truncated() {
+        let dump = "H4sI".repeat(1000);
+        let problem = json!({"dump": dump, "meta": {"rows": 3, "tags": []}});
+
+        assert_eq!(
+            pretty(&problem, false),
+            format!(
+                "{{\n  \"dump\": \"{}...\" (4000 bytes),\n  \"meta\": {{\n    \"rows\": 3,\n    \"tags\": []\n  }}\n}}",
+                &dump[..60]
+            )
+        );
+
+        // short values are printed exactly as serde_json would
+        let short = json!({"difficulty": 8, "block": {"data": [["a", 1]], "nonce": null}});
+        assert_eq!(
+            pretty(&short, false),
+            serde_json::to_string_pretty(&short).unwrap()
+        );
+
+        assert!(pretty(&json!("é".repeat(50)), false).ends_with("...\" (100 bytes)"));
+        assert_eq!(pretty(&json!(true), true), "\x1b[33mtrue\x1b[0m");
+    }
+}
@@ -0,0 +1,106 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use tokio::task::JoinHandle;
+use tracing::info;
+
+use crate::HackatticError;
+
+/// How a long solve is getting on, shared between the solver updating it
+/// and whoever shows it or wants the solve stopped. Clones share one handle,
+/// and a default one nobody else holds costs next to nothing, so solvers
+/// update theirs whether or not anyone is watching.
+#[derive(Debug, Clone, Default)]
+pub struct Progress {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    done: AtomicU64,
+    total: AtomicU64,
+    cancelled: AtomicBool,
+}
+
+/// How often `Progress::watch` logs.
+const WATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+impl Progress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How many units of work the solve expects, 0 if it can't tell.
+    pub fn set_total(&self, total: u64) {
+        self.inner.total.store(total, Ordering::Relaxed);
+    }
+
+    pub fn advance(&self, done: u64) {
+        self.inner.done.fetch_add(done, Ordering::Relaxed);
+    }
+
+    /// Replaces the count of units done, for a solver starting over or
+    /// resuming partway.
+    pub fn set_done(&self, done: u64) {
+        self.inner.done.store(done, Ordering::Relaxed);
+    }
+
+    /// Units done so far, and the expected total if one was set.
+    pub fn position(&self) -> (u64, Option<u64>) {
+        let total = self.inner.total.load(Ordering::Relaxed);
+        (
+            self.inner.done.load(Ordering::Relaxed),
+            (total > 0).then_some(total),
+        )
+    }
+
+    /// Asks the solver to stop at its next `check`.
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// For solvers to call between units of work, fails once cancelled.
+    pub fn check(&self) -> Result<(), HackatticError> {
+        match self.is_cancelled() {
+            true => Err(HackatticError::Cancelled),
+            false => Ok(()),
+        }
+    }
+
+    /// Logs the position every few seconds until the returned guard drops.
+    pub fn watch(&self, name: &'static str) -> WatchGuard {
+        let progress = self.clone();
+        WatchGuard(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(WATCH_INTERVAL);
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                match progress.position() {
+                    (done, Some(total)) => info!(
+                        "{name}: {done}/{total} ({:.1}%)",
+                        done as f64 * 100.0 / total as f64
+                    ),
+                    (done, None) => info!("{name}: {done} done"),
+                }
+            }
+        }))
+    }
+}
+
+/// Stops a `Progress::watch` when dropped.
+pub struct WatchGuard(JoinHandle<()>);
+
+impl Drop for WatchGuard {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
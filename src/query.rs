@@ -0,0 +1,144 @@
+use std::future::Future;
+
+use anyhow::{Context, Result};
+use deadpool_postgres::Object;
+use tokio_postgres::{types::ToSql, Row};
+
+use crate::HackatticError;
+
+/// Anything DB backed challenges read their answers from with a
+/// parameterized statement.
+pub trait QueryStore {
+    type Row;
+    type Param: ?Sized;
+
+    fn query(
+        &self,
+        statement: &str,
+        params: &[&Self::Param],
+    ) -> impl Future<Output = Result<Vec<Self::Row>>> + Send;
+}
+
+impl QueryStore for Object {
+    type Row = Row;
+    type Param = dyn ToSql + Sync;
+
+    async fn query(&self, statement: &str, params: &[&Self::Param]) -> Result<Vec<Row>> {
+        Ok(tokio_postgres::Client::query(self, statement, params).await?)
+    }
+}
+
+/// Runs `statement` and maps every row with `map`. No matching rows is an
+/// empty `Vec`, which can be a valid answer.
+pub async fn query_rows<S, T>(
+    store: &S,
+    statement: &str,
+    params: &[&S::Param],
+    map: impl FnMut(&S::Row) -> Result<T>,
+) -> Result<Vec<T>>
+where
+    S: QueryStore + Sync,
+{
+    fetch(store, statement, params)
+        .await?
+        .iter()
+        .map(map)
+        .collect::<Result<_>>()
+        .with_context(|| format!("unexpected row from `{statement}`"))
+}
+
+/// Like `query_rows`, for statements where only the first row matters. No
+/// matching rows is `HackatticError::NotFound`, there is nothing to map.
+pub async fn query_first<S, T>(
+    store: &S,
+    statement: &str,
+    params: &[&S::Param],
+    map: impl FnOnce(&S::Row) -> Result<T>,
+) -> Result<T>
+where
+    S: QueryStore + Sync,
+{
+    let rows = fetch(store, statement, params).await?;
+    let first = rows
+        .first()
+        .ok_or_else(|| HackatticError::NotFound(format!("no rows match `{statement}`")))?;
+    map(first).with_context(|| format!("unexpected row from `{statement}`"))
+}
+
+async fn fetch<S>(store: &S, statement: &str, params: &[&S::Param]) -> Result<Vec<S::Row>>
+where
+    S: QueryStore + Sync,
+{
+    store
+        .query(statement, params)
+        .await
+        .with_context(|| format!("failed to run `{statement}`"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+
+    use anyhow::Result;
+
+    use super::{query_first, query_rows, QueryStore};
+    use crate::HackatticError;
+
+    /// `(id, name)` rows, `params[0]` is the smallest id returned.
+    struct MemoryStore(Vec<(i32, &'static str)>);
+
+    impl QueryStore for MemoryStore {
+        type Row = (i32, &'static str);
+        type Param = i32;
+
+        fn query(
+            &self,
+            _statement: &str,
+            params: &[&i32],
+        ) -> impl Future<Output = Result<Vec<Self::Row>>> + Send {
+            let rows = self.0.iter().filter(|row| row.0 >= *params[0]).copied();
+            std::future::ready(Ok(rows.collect()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rows_are_mapped_and_no_first_is_not_found() {
+        let store = MemoryStore(vec![(1, "dead"), (2, "alive"), (3, "living")]);
+        let statement = "select name from people where id >= $1";
+
+        let names = query_rows(&store, statement, &[&2], |row| Ok(row.1.to_string()))
+            .await
+            .unwrap();
+        assert_eq!(names, ["alive", "living"]);
+
+        let none = query_rows(&store, statement, &[&4], |row| Ok(row.0))
+            .await
+            .unwrap();
+        assert!(none.is_empty());
+
+        let id = query_first(&store, statement, &[&2], |row| Ok(row.0))
+            .await
+            .unwrap();
+        assert_eq!(id, 2);
+
+        let err = query_first(&store, statement, &[&4], |row| Ok(row.0))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<HackatticError>(),
+            Some(HackatticError::NotFound(_))
+        ));
+        assert!(err.to_string().contains(statement), "{err}");
+
+        let err = query_rows(&store, statement, &[&1], |row| match row.1 {
+            "dead" => anyhow::bail!("row {} is dead", row.0),
+            name => Ok(name),
+        })
+        .await
+        .unwrap_err();
+        assert_eq!(
+            format!("{err:#}"),
+            format!("unexpected row from `{statement}`: row 1 is dead")
+        );
+    }
+}
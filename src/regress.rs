@@ -0,0 +1,204 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use tracing::{error, info, warn};
+
+use crate::{
+    backup_restore::{self, BackupRestore},
+    hackattic_context::HackatticContext,
+    help_me_unpack::HelpMeUnpack,
+    mini_miner::MiniMiner,
+    password_hashing::PasswordHashing,
+    runner::check_schema,
+    tales_of_ssl::TalesOfSsl,
+    Hackattic,
+};
+
+/// A captured problem together with the answer it is expected to produce.
+/// `answer` is `None` for challenges whose answer is not reproducible
+/// (e.g. a certificate carrying the current time), in which case only a
+/// successful solve is checked.
+#[derive(Deserialize, Debug)]
+pub struct Fixture {
+    pub problem: Value,
+    pub answer: Option<Value>,
+}
+
+impl Fixture {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("unable to read fixture {}", path.display()))?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("unable to parse fixture {}", path.display()))
+    }
+}
+
+pub fn fixture_path<T: Hackattic>(dir: &Path) -> PathBuf {
+    dir.join(format!("{}.json", T::NAME))
+}
+
+/// Solves the stored problem offline, configured from `context` as a real
+/// solve would be, and compares the answer against the stored one.
+pub async fn check<T: Hackattic>(fixture: &Fixture, context: &HackatticContext) -> Result<()> {
+    let mut problem = serde_json::from_value(fixture.problem.clone())
+        .with_context(|| format!("fixture problem does not match {}", T::NAME))?;
+    T::configure(&mut problem, context)?;
+    let answer = T::solve(problem).await?;
+    T::validate_answer(&answer).context("answer is invalid")?;
+
+    // compare what would be posted, not the in-memory representation
+    let actual: Value = serde_json::from_str(&serde_json::to_string(&answer)?)?;
+    if let Some(schema) = T::answer_schema() {
+        check_schema(&schema, &actual).context("answer does not match its schema")?;
+    }
+
+    match &fixture.answer {
+        Some(expected) if *expected != actual => {
+            anyhow::bail!("answer changed:\n{}", diff(expected, &actual))
+        }
+        _ => Ok(()),
+    }
+}
+
+async fn check_fixture<T: Hackattic>(dir: &Path, context: &HackatticContext) -> Result<()> {
+    let fixture = Fixture::load(&fixture_path::<T>(dir))?;
+    check::<T>(&fixture, context).await
+}
+
+/// Runs every solver against its fixture in `dir`, failing if any answer
+/// differs from the stored one. backup_restore is skipped where psql or
+/// Postgres is missing.
+pub async fn run(dir: &Path) -> Result<()> {
+    let context = HackatticContext::offline()?;
+    let backup_restore = match backup_restore::missing_prerequisite(&context).await {
+        Some(reason) => {
+            warn!("{}: skipped, {reason}", BackupRestore::NAME);
+            None
+        }
+        None => Some(check_fixture::<BackupRestore>(dir, &context).await),
+    };
+    let results = [
+        (
+            HelpMeUnpack::NAME,
+            check_fixture::<HelpMeUnpack>(dir, &context).await,
+        ),
+        (
+            MiniMiner::NAME,
+            check_fixture::<MiniMiner>(dir, &context).await,
+        ),
+        (
+            PasswordHashing::NAME,
+            check_fixture::<PasswordHashing>(dir, &context).await,
+        ),
+        (
+            TalesOfSsl::NAME,
+            check_fixture::<TalesOfSsl>(dir, &context).await,
+        ),
+    ];
+
+    let mut failed = 0;
+    let backup_restore = backup_restore.map(|result| (BackupRestore::NAME, result));
+    for (name, result) in results.into_iter().chain(backup_restore) {
+        match result {
+            Ok(()) => info!("{name}: ok"),
+            Err(e) => {
+                error!("{name}: {e:#}");
+                failed += 1;
+            }
+        }
+    }
+
+    if failed > 0 {
+        anyhow::bail!("{failed} regression(s) failed")
+    }
+    Ok(())
+}
+
+/// Line-per-field diff of two JSON values, `-` for expected and `+` for actual.
+pub fn diff(expected: &Value, actual: &Value) -> String {
+    let mut lines = Vec::new();
+    diff_into("", expected, actual, &mut lines);
+    lines.join("\n")
+}
+
+fn diff_into(path: &str, expected: &Value, actual: &Value, lines: &mut Vec<String>) {
+    match (expected, actual) {
+        (Value::Object(e), Value::Object(a)) => {
+            for (key, e_val) in e {
+                let path = format!("{path}.{key}");
+                match a.get(key) {
+                    Some(a_val) => diff_into(&path, e_val, a_val, lines),
+                    None => lines.push(format!("- {path}: {e_val}")),
+                }
+            }
+            for (key, a_val) in a {
+                if !e.contains_key(key) {
+                    lines.push(format!("+ {path}.{key}: {a_val}"));
+                }
+            }
+        }
+        _ if expected != actual => {
+            let path = if path.is_empty() { "." } else { path };
+            lines.push(format!("- {path}: {expected}"));
+            lines.push(format!("+ {path}: {actual}"));
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use serde_json::json;
+
+    use super::{check, check_fixture, diff, fixture_path, Fixture};
+    use crate::{
+        config::Config, help_me_unpack::HelpMeUnpack, mini_miner::MiniMiner,
+        password_hashing::PasswordHashing, tales_of_ssl::TalesOfSsl, test_util,
+    };
+
+    fn fixtures_dir() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures")
+    }
+
+    // backup_restore is left out as it needs a running postgres
+    #[tokio::test]
+    async fn test_offline_fixtures_pass() {
+        let dir = fixtures_dir();
+        let context = test_util::context("", Config::default());
+        check_fixture::<HelpMeUnpack>(&dir, &context).await.unwrap();
+        check_fixture::<MiniMiner>(&dir, &context).await.unwrap();
+        check_fixture::<PasswordHashing>(&dir, &context)
+            .await
+            .unwrap();
+        check_fixture::<TalesOfSsl>(&dir, &context).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_changed_answer_fails_with_diff() {
+        let mut fixture = Fixture::load(&fixture_path::<HelpMeUnpack>(&fixtures_dir())).unwrap();
+        fixture.answer.as_mut().unwrap()["short"] = json!(4321);
+
+        let context = test_util::context("", Config::default());
+        let err = check::<HelpMeUnpack>(&fixture, &context).await.unwrap_err();
+        let message = format!("{err:#}");
+        assert!(message.contains("- .short: 4321"), "{message}");
+        assert!(message.contains("+ .short: -1234"), "{message}");
+    }
+
+    #[test]
+    fn test_diff() {
+        let expected = json!({"a": 1, "b": {"c": "x"}, "d": true});
+        let actual = json!({"a": 1, "b": {"c": "y"}, "e": null});
+        assert_eq!(
+            diff(&expected, &actual),
+            "- .b.c: \"x\"\n+ .b.c: \"y\"\n- .d: true\n+ .e: null"
+        );
+    }
+}
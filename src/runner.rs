@@ -0,0 +1,1443 @@
+use std::{
+    collections::HashMap,
+    fs,
+    future::Future,
+    io::{Read, Write},
+    path::Path,
+    time::Instant,
+};
+
+use anyhow::Context;
+use flate2::{write::GzEncoder, Compression};
+use reqwest::{
+    header::{
+        HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, CONTENT_ENCODING, CONTENT_TYPE,
+        PROXY_AUTHORIZATION,
+    },
+    Client, ClientBuilder, Request, RequestBuilder, StatusCode,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use tracing::*;
+
+use crate::{
+    hackattic_context::HackatticContext, metrics, regress, secret::Zeroizing, Hackattic,
+    HackatticError, Phase, SolveOptions, SolveOutcome, SubmitFormat,
+};
+
+pub const USER_AGENT: &str = concat!("hackattic-rs/", env!("CARGO_PKG_VERSION"));
+const HEADER_ENV_PREFIX: &str = "HA_HEADER_";
+const REDACTED: &str = "<redacted>";
+
+/// Builds the HTTP client, adding a header for every `HA_HEADER_*` variable,
+/// e.g. `HA_HEADER_PROXY_AUTHORIZATION` sets `proxy-authorization`.
+pub fn build_client() -> anyhow::Result<Client> {
+    Ok(client_builder(std::env::vars())?.build()?)
+}
+
+fn client_builder(vars: impl Iterator<Item = (String, String)>) -> anyhow::Result<ClientBuilder> {
+    let mut headers = HeaderMap::new();
+    for (key, value) in vars {
+        if let Some(name) = key.strip_prefix(HEADER_ENV_PREFIX) {
+            let name = name.to_lowercase().replace('_', "-");
+            headers.insert(
+                HeaderName::try_from(name.as_str())
+                    .with_context(|| format!("invalid header name in {key}"))?,
+                HeaderValue::try_from(value).with_context(|| format!("invalid value for {key}"))?,
+            );
+        }
+    }
+
+    Ok(ClientBuilder::new()
+        .cookie_store(true)
+        .user_agent(USER_AGENT)
+        .default_headers(headers))
+}
+
+pub fn problem_url(base_url: &str, name: &str) -> String {
+    format!("{}/challenges/{}/problem/", base_url, name)
+}
+
+pub fn solve_url(base_url: &str, name: &str) -> String {
+    format!("{}/challenges/{}/solve/", base_url, name)
+}
+
+/// Query parameters of every request. `SolveOptions::extra_params` are added,
+/// but can't replace the token or playground flag.
+fn query<'a>(
+    context: &'a HackatticContext,
+    options: &'a SolveOptions,
+) -> HashMap<&'a str, &'a str> {
+    let mut map: HashMap<_, _> = options
+        .extra_params
+        .iter()
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+        .collect();
+    map.insert("access_token", context.access_token.as_str());
+    if context.playground {
+        map.insert("playground", "1");
+    }
+    map
+}
+
+/// Fetches, solves and submits a problem, re-running all three on a rejection
+/// up to `SolveOptions::resubmit_on_reject` times if the challenge allows it.
+/// A problem that expired before the answer arrived is fetched again up to
+/// `SolveOptions::refetch_on_expired` times, costly challenge or not, since
+/// the old answer can never pass. Each call counts as one attempt in the
+/// metrics, however many times it resubmitted.
+pub async fn solve<T: Hackattic>(
+    client: Client,
+    context: &HackatticContext,
+    options: &SolveOptions,
+) -> Result<String, HackatticError> {
+    let outcome = solve_retrying::<T>(client, context, options).await;
+    metrics::global().record_attempt(outcome.is_ok());
+    outcome
+}
+
+async fn solve_retrying<T: Hackattic>(
+    client: Client,
+    context: &HackatticContext,
+    options: &SolveOptions,
+) -> Result<String, HackatticError> {
+    let mut resubmits = options.resubmit_on_reject;
+    if resubmits > 0 && !T::RESUBMITTABLE {
+        warn!("{} is too costly to solve again, not resubmitting", T::NAME);
+        resubmits = 0;
+    }
+
+    let mut refetches = options.refetch_on_expired;
+    loop {
+        let outcome = solve_once::<T>(client.clone(), context, options).await;
+        let retry = match &outcome {
+            Err(HackatticError::Rejected(reason)) if refetches > 0 && is_expired(reason) => {
+                refetches -= 1;
+                format!("problem expired ({reason}), solving a fresh one")
+            }
+            Err(HackatticError::Rejected(reason)) if resubmits > 0 => {
+                resubmits -= 1;
+                format!("rejected ({reason}), solving again")
+            }
+            _ => return outcome,
+        };
+        if options.retry_budget.as_ref().is_some_and(|b| !b.try_take()) {
+            warn!(
+                "{} {retry} skipped, the shared retry budget is spent",
+                T::NAME
+            );
+            return outcome;
+        }
+        warn!("{} {retry}", T::NAME);
+    }
+}
+
+/// Whether a rejection says the problem went stale rather than that the
+/// answer was wrong.
+fn is_expired(reason: &str) -> bool {
+    let reason = reason.to_lowercase();
+    ["expired", "stale", "too late"]
+        .iter()
+        .any(|marker| reason.contains(marker))
+}
+
+async fn solve_once<T: Hackattic>(
+    client: Client,
+    context: &HackatticContext,
+    options: &SolveOptions,
+) -> Result<String, HackatticError> {
+    let url = T::problem_url(&context.base_url);
+    // the raw problem carries whatever secrets the parsed one wipes
+    let body = Zeroizing::new(
+        phase::<T, _>(
+            Phase::Fetch,
+            options,
+            fetch_problem(&client, context, T::NAME, &url, options),
+        )
+        .await?,
+    );
+
+    let ans = {
+        let _watch = options
+            .progress
+            .as_ref()
+            .map(|progress| progress.watch(T::NAME));
+        phase::<T, _>(Phase::Solve, options, answer::<T>(&body, context, options)).await?
+    };
+    if let Some(path) = &options.save_answer {
+        save_answer::<T>(path, &ans)?;
+    }
+    let response = phase::<T, _>(
+        Phase::Submit,
+        options,
+        submit_answer::<T>(&client, context, &ans, options),
+    )
+    .await;
+    if let (true, Err(HackatticError::Rejected(reason))) = (options.explain_failure, &response) {
+        match explain_failure::<T>(&ans).as_slice() {
+            [] => warn!("{} rejected ({reason}), no likely cause found", T::NAME),
+            hints => warn!(
+                "{} rejected ({reason}), likely because:\n  - {}",
+                T::NAME,
+                hints.join("\n  - ")
+            ),
+        }
+    }
+    // only an accepted answer, a rejected one is about to be solved again
+    if let (true, Ok(_)) = (options.print_answer, &response) {
+        println!(
+            "{}",
+            serde_json::to_string(&ans).context("Unable to serialize")?
+        );
+    }
+    response
+}
+
+/// What `validate_answer` and `diagnose` make of a rejected answer.
+pub fn explain_failure<T: Hackattic>(ans: &T::Answer) -> Vec<String> {
+    let mut hints = Vec::new();
+    if let Err(e) = T::validate_answer(ans) {
+        hints.push(format!("{e:#}"));
+    }
+    hints.extend(T::diagnose(ans));
+    hints
+}
+
+/// Runs one phase of a solve, logging how long it took and failing it once
+/// it overruns its deadline. The submit phase is never cut short: once the
+/// answer is on its way the server may have accepted it, and calling that a
+/// timeout would invite a retry, so an overrun there is only warned about.
+async fn phase<T: Hackattic, R>(
+    phase: Phase,
+    options: &SolveOptions,
+    work: impl Future<Output = Result<R, HackatticError>>,
+) -> Result<R, HackatticError> {
+    let deadline = options.deadlines.get(phase);
+    let started = Instant::now();
+    let result = match deadline {
+        Some(deadline) if phase != Phase::Submit => tokio::time::timeout(deadline, work)
+            .await
+            .map_err(|_| HackatticError::TimedOut(phase, deadline))?,
+        _ => work.await,
+    };
+    let elapsed = started.elapsed();
+    debug!("{} {phase} took {elapsed:?}", T::NAME);
+
+    match deadline {
+        Some(deadline) if elapsed > deadline && phase == Phase::Submit => {
+            warn!(
+                "{} submit took {elapsed:?}, past its {deadline:?} deadline",
+                T::NAME
+            );
+            result
+        }
+        // a solver that blocks instead of awaiting can't be cut short, only
+        // caught afterwards
+        Some(deadline) if elapsed > deadline => Err(HackatticError::TimedOut(phase, deadline)),
+        _ => result,
+    }
+}
+
+/// Dumps an answer as JSON for `load_answer`.
+pub fn save_answer<T: Hackattic>(path: &Path, ans: &T::Answer) -> anyhow::Result<()> {
+    let json = serde_json::to_string(ans).context("Unable to serialize")?;
+    fs::write(path, json)
+        .with_context(|| format!("unable to save answer to {}", path.display()))?;
+    info!("saved {} answer to {}", T::NAME, path.display());
+    Ok(())
+}
+
+/// Reads back a dumped answer, which must still fit the challenge's answer
+/// type before it is worth submitting again.
+pub fn load_answer<T: Hackattic>(path: &Path) -> anyhow::Result<T::Answer> {
+    let json = fs::read_to_string(path)
+        .with_context(|| format!("unable to read answer {}", path.display()))?;
+    serde_json::from_str(&json)
+        .with_context(|| format!("{} is not a {} answer", path.display(), T::NAME))
+}
+
+/// Parses a problem body and solves it, without touching the network.
+pub async fn answer<T: Hackattic>(
+    body: &str,
+    context: &HackatticContext,
+    options: &SolveOptions,
+) -> Result<T::Answer, HackatticError> {
+    let mut problem = parse_problem(T::NAME, body)?;
+    T::configure(&mut problem, context)
+        .with_context(|| format!("failed to configure {}", T::NAME))?;
+    T::apply_options(&mut problem, options);
+    if context.playground {
+        T::override_problem(&mut problem, options);
+    }
+
+    info!("{:?}", problem);
+
+    let ans = T::solve(problem).await?;
+    T::validate_answer(&ans).with_context(|| format!("{} answer is invalid", T::NAME))?;
+
+    let string = serde_json::to_string(&ans).context("Unable to serialize")?;
+
+    info!("{}", string);
+
+    if options.validate_schema {
+        if let Some(schema) = T::answer_schema() {
+            let value = serde_json::to_value(&ans).context("Unable to serialize")?;
+            check_schema(&schema, &value)
+                .with_context(|| format!("{} answer does not match its schema", T::NAME))?;
+        }
+    }
+    Ok(ans)
+}
+
+pub async fn submit_answer<T: Hackattic>(
+    client: &Client,
+    context: &HackatticContext,
+    ans: &T::Answer,
+    options: &SolveOptions,
+) -> Result<String, HackatticError> {
+    let request = client
+        .post(T::solve_url(&context.base_url))
+        .query(&query(context, options));
+    let request = if options.answer_renames.is_empty() {
+        encode_answer::<T>(request, ans)?
+    } else {
+        let mut value = serde_json::to_value(ans).context("Unable to serialize")?;
+        rename_fields(&mut value, &options.answer_renames);
+        info!("renamed answer {}", value);
+        encode_answer::<T>(request, &value)?
+    };
+    let body = submit(request, options).await?;
+    accepted(T::interpret_response(&body))
+}
+
+fn encode_answer<T: Hackattic>(
+    request: RequestBuilder,
+    ans: &impl Serialize,
+) -> anyhow::Result<RequestBuilder> {
+    Ok(match T::SUBMIT_AS {
+        SubmitFormat::Json if T::COMPRESS_ANSWER => {
+            let json = serde_json::to_vec(ans).context("Unable to serialize")?;
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(&json)?;
+            let gzipped = encoder.finish()?;
+            debug!("gzipped {} byte answer to {}", json.len(), gzipped.len());
+            request
+                .header(CONTENT_TYPE, "application/json")
+                .header(CONTENT_ENCODING, "gzip")
+                .body(gzipped)
+        }
+        SubmitFormat::Json => request.json(ans),
+        SubmitFormat::Form => request.form(ans),
+    })
+}
+
+/// Renames top level answer fields for `--answer-rename`.
+fn rename_fields(answer: &mut Value, renames: &[(String, String)]) {
+    let Value::Object(fields) = answer else {
+        warn!("answer is not an object, nothing to rename");
+        return;
+    };
+    for (old, new) in renames {
+        match fields.remove(old) {
+            Some(value) => {
+                fields.insert(new.clone(), value);
+            }
+            None => warn!("answer has no field {old} to rename"),
+        }
+    }
+}
+
+/// Reads a piped problem, which must not be empty.
+pub fn read_problem(mut reader: impl Read) -> anyhow::Result<String> {
+    let mut body = String::new();
+    reader
+        .read_to_string(&mut body)
+        .context("unable to read problem from stdin")?;
+    if body.trim().is_empty() {
+        anyhow::bail!("no problem JSON on stdin");
+    }
+    Ok(body)
+}
+
+/// Fetches the problem of a challenge without a typed solver.
+pub async fn fetch_raw_problem(
+    client: &Client,
+    context: &HackatticContext,
+    name: &str,
+    options: &SolveOptions,
+) -> Result<Value, HackatticError> {
+    let url = problem_url(&context.base_url, name);
+    let body = fetch_problem(client, context, name, &url, options).await?;
+    Ok(parse_problem(name, &body)?)
+}
+
+/// Fetches a challenge's problem twice and diffs the two fetches, to tell
+/// whether the server regenerates the problem on every fetch. Empty if both
+/// fetches returned the same problem.
+pub async fn diff_problems(
+    client: &Client,
+    context: &HackatticContext,
+    name: &str,
+    options: &SolveOptions,
+) -> Result<String, HackatticError> {
+    let first = fetch_raw_problem(client, context, name, options).await?;
+    let second = fetch_raw_problem(client, context, name, options).await?;
+    Ok(regress::diff(&first, &second))
+}
+
+/// Posts a hand written answer to a challenge exactly as given.
+pub async fn submit_raw_answer(
+    client: &Client,
+    context: &HackatticContext,
+    name: &str,
+    answer: &str,
+    options: &SolveOptions,
+) -> Result<String, HackatticError> {
+    serde_json::from_str::<Value>(answer).context("answer is not valid JSON")?;
+
+    let request = client
+        .post(solve_url(&context.base_url, name))
+        .query(&query(context, options))
+        .header(CONTENT_TYPE, "application/json")
+        .body(answer.to_string());
+    let body = submit(request, options).await?;
+    accepted(Ok(interpret_response(&body)))
+}
+
+/// How much of the body is quoted on either side of a parse error.
+const SNIPPET_RADIUS: usize = 40;
+
+/// Keys a problem may be nested under, next to metadata about it.
+const WRAPPER_KEYS: &[&str] = &["problem", "data"];
+
+/// Parses a problem body, naming the challenge and quoting the body around
+/// the failure if it doesn't fit. A problem nested under one of
+/// `WRAPPER_KEYS` is accepted too.
+fn parse_problem<P: DeserializeOwned>(name: &str, body: &str) -> anyhow::Result<P> {
+    let e = match serde_json::from_str(body) {
+        Ok(problem) => return Ok(problem),
+        Err(e) => e,
+    };
+    if let Some((key, problem)) = unwrap_problem(body) {
+        debug!("{name} problem was wrapped in {key:?}");
+        return Ok(problem);
+    }
+
+    let offset = error_offset(body, e.line(), e.column());
+    Err(anyhow::anyhow!(
+        "unable to parse {name} problem ({} bytes): {e}, near `{}`",
+        body.len(),
+        snippet(body, offset)
+    ))
+}
+
+fn unwrap_problem<P: DeserializeOwned>(body: &str) -> Option<(&'static str, P)> {
+    let Value::Object(object) = serde_json::from_str(body).ok()? else {
+        return None;
+    };
+    WRAPPER_KEYS.iter().find_map(|&key| {
+        let problem = P::deserialize(object.get(key)?).ok()?;
+        Some((key, problem))
+    })
+}
+
+/// Byte offset of serde_json's 1 based line and column.
+fn error_offset(body: &str, line: usize, column: usize) -> usize {
+    let line_start: usize = body
+        .split_inclusive('\n')
+        .take(line.saturating_sub(1))
+        .map(str::len)
+        .sum();
+    (line_start + column.saturating_sub(1)).min(body.len())
+}
+
+fn snippet(body: &str, offset: usize) -> String {
+    let mut start = offset.saturating_sub(SNIPPET_RADIUS);
+    while !body.is_char_boundary(start) {
+        start -= 1;
+    }
+    let mut end = (offset + SNIPPET_RADIUS).min(body.len());
+    while !body.is_char_boundary(end) {
+        end += 1;
+    }
+
+    // escape line breaks so the snippet stays on the error's line
+    let mut snippet: String = body[start..end]
+        .chars()
+        .flat_map(|c| {
+            if c.is_control() {
+                c.escape_debug().collect()
+            } else {
+                vec![c]
+            }
+        })
+        .collect();
+    if start > 0 {
+        snippet.insert_str(0, "...");
+    }
+    if end < body.len() {
+        snippet.push_str("...");
+    }
+    snippet
+}
+
+pub fn check_schema(schema: &Value, answer: &Value) -> anyhow::Result<()> {
+    let validator = jsonschema::validator_for(schema).context("invalid answer schema")?;
+    let errors: Vec<_> = validator
+        .iter_errors(answer)
+        .map(|e| e.to_string())
+        .collect();
+    if !errors.is_empty() {
+        anyhow::bail!(errors.join("; "));
+    }
+    Ok(())
+}
+
+pub(crate) async fn fetch_problem(
+    client: &Client,
+    context: &HackatticContext,
+    name: &str,
+    url: &str,
+    options: &SolveOptions,
+) -> Result<String, HackatticError> {
+    debug!("{}", url);
+
+    let (status, body) = send(client.get(url).query(&query(context, options)), options).await?;
+
+    debug!("{} {:?}", status, body);
+
+    match status {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(HackatticError::AuthFailed(body)),
+        StatusCode::NOT_FOUND => Err(HackatticError::NotFound(name.to_string())),
+        _ => Ok(body),
+    }
+}
+
+async fn submit(request: RequestBuilder, options: &SolveOptions) -> Result<String, HackatticError> {
+    let (_, body) = send(request, options).await?;
+    Ok(body)
+}
+
+fn accepted(outcome: anyhow::Result<SolveOutcome>) -> Result<String, HackatticError> {
+    match outcome.context("unable to interpret the solve response")? {
+        SolveOutcome::Accepted(body) => Ok(body),
+        SolveOutcome::Rejected(reason) => Err(HackatticError::Rejected(reason)),
+    }
+}
+
+/// The default reading of a solve response: rejected if it names a reason,
+/// accepted otherwise.
+pub fn interpret_response(body: &str) -> SolveOutcome {
+    match rejection_reason(body) {
+        Some(reason) => SolveOutcome::Rejected(reason),
+        None => SolveOutcome::Accepted(body.to_string()),
+    }
+}
+
+/// Sends `request` and reads the response body, first logging the request
+/// in full for `SolveOptions::dump_request` and recording both on
+/// `SolveOptions::cassette`.
+async fn send(
+    request: RequestBuilder,
+    options: &SolveOptions,
+) -> reqwest::Result<(StatusCode, String)> {
+    let (client, request) = request.build_split();
+    let request = request?;
+    if options.dump_request {
+        info!("{}", describe_request(&request));
+    }
+    let recorded = options.cassette.as_ref().and_then(|_| request.try_clone());
+    // the path alone, the query carries the access token
+    let label = format!("{} {}", request.method(), request.url().path());
+    let started = Instant::now();
+    let resp = client.execute(request).await;
+    metrics::global().observe_request(&label, started.elapsed());
+    let resp = resp?;
+    let status = resp.status();
+    let body = resp.text().await?;
+    if let (Some(cassette), Some(request)) = (&options.cassette, recorded) {
+        cassette.record(&request, status, &body);
+    }
+    Ok((status, body))
+}
+
+/// The request line, headers and body, with the access token redacted.
+/// Headers the client adds itself while sending aren't included.
+fn describe_request(request: &Request) -> String {
+    let mut url = request.url().clone();
+    let pairs: Vec<_> = url
+        .query_pairs()
+        .map(|(key, value)| match key.as_ref() {
+            "access_token" => (key.into_owned(), REDACTED.to_string()),
+            _ => (key.into_owned(), value.into_owned()),
+        })
+        .collect();
+    if !pairs.is_empty() {
+        url.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+
+    let mut out = format!("{} {}", request.method(), url);
+    for (name, value) in request.headers() {
+        let value = if name == AUTHORIZATION || name == PROXY_AUTHORIZATION {
+            REDACTED
+        } else {
+            value.to_str().unwrap_or("<binary>")
+        };
+        out.push_str(&format!("\n{name}: {value}"));
+    }
+    if let Some(body) = request.body().and_then(|body| body.as_bytes()) {
+        out.push_str("\n\n");
+        out.push_str(&String::from_utf8_lossy(body));
+    }
+    out
+}
+
+pub(crate) fn rejection_reason(resp: &str) -> Option<String> {
+    let value: Value = serde_json::from_str(resp).ok()?;
+    value
+        .get("rejected")
+        .or_else(|| value.get("error"))
+        .map(|reason| match reason {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Read, sync::Arc, time::Duration};
+
+    use flate2::read::GzDecoder;
+    use serde::{Deserialize, Serialize};
+    use wiremock::{
+        matchers::{body_json, header, method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use serde_json::json;
+
+    use super::{
+        answer, check_schema, client_builder, diff_problems, is_expired, load_answer,
+        parse_problem, read_problem, rejection_reason, rename_fields, save_answer, solve,
+        submit_answer, submit_raw_answer, USER_AGENT,
+    };
+    use crate::{
+        batch::RetryBudget,
+        cassette::Cassette,
+        config::Config,
+        hackattic_context::HackatticContext,
+        help_me_unpack::HelpMeUnpack,
+        mini_miner::{MiniMiner, MiniMinerAnswer},
+        password_hashing::PasswordHashing,
+        test_util::{self, capture_logs, replay},
+        Hackattic, HackatticError, Phase, PhaseDeadlines, SolveOptions, SolveOutcome, SubmitFormat,
+    };
+
+    struct FormChallenge;
+
+    #[derive(Deserialize, Debug)]
+    struct FormProblem {
+        word: String,
+    }
+
+    #[derive(Serialize, Deserialize, Debug)]
+    struct FormAnswer {
+        word: String,
+    }
+
+    impl Hackattic for FormChallenge {
+        const NAME: &'static str = "form_challenge";
+        const SUBMIT_AS: SubmitFormat = SubmitFormat::Form;
+        type Problem = FormProblem;
+        type Answer = FormAnswer;
+
+        async fn solve(problem: Self::Problem) -> anyhow::Result<Self::Answer> {
+            Ok(FormAnswer {
+                word: problem.word.to_uppercase(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_recorded_exchange_replays() {
+        let recorded = Cassette::load(
+            &std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("fixtures/cassettes/help_me_unpack.json"),
+        )
+        .unwrap();
+        let server = MockServer::start().await;
+        replay(&server, &recorded).await;
+
+        let cassette = Cassette::default();
+        let options = SolveOptions {
+            cassette: Some(cassette.clone()),
+            ..Default::default()
+        };
+        let response = solve::<HelpMeUnpack>(
+            reqwest::Client::new(),
+            &test_util::context(&server.uri(), Config::default()),
+            &options,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response, r#"{"result": "passed"}"#);
+        // recording the replay gives back the cassette, token redacted
+        assert_eq!(cassette.interactions(), recorded.interactions());
+    }
+
+    #[tokio::test]
+    async fn test_form_challenge_posts_urlencoded() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/challenges/form_challenge/problem/"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"word": "hi"}"#))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/challenges/form_challenge/solve/"))
+            .and(header("content-type", "application/x-www-form-urlencoded"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"passed": true}"#))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        solve::<FormChallenge>(
+            reqwest::Client::new(),
+            &test_util::context(&server.uri(), Config::default()),
+            &Default::default(),
+        )
+        .await
+        .unwrap();
+
+        let requests = server.received_requests().await.unwrap();
+        assert_eq!(requests.last().unwrap().body, b"word=HI");
+    }
+
+    #[tokio::test]
+    async fn test_resubmit_after_flaky_rejection() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/challenges/form_challenge/problem/"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"word": "hi"}"#))
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/challenges/form_challenge/solve/"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_string(r#"{"rejected": "service not up"}"#),
+            )
+            .up_to_n_times(1)
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/challenges/form_challenge/solve/"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"passed": true}"#))
+            .mount(&server)
+            .await;
+
+        let options = SolveOptions {
+            resubmit_on_reject: 2,
+            ..Default::default()
+        };
+        let before = metrics_attempts();
+        let response = solve::<FormChallenge>(
+            reqwest::Client::new(),
+            &test_util::context(&server.uri(), Config::default()),
+            &options,
+        )
+        .await
+        .unwrap();
+        assert_eq!(response, r#"{"passed": true}"#);
+        // other tests solve concurrently, so only a lower bound holds
+        assert!(metrics_attempts() > before);
+    }
+
+    fn metrics_attempts() -> f64 {
+        crate::metrics::global()
+            .render()
+            .lines()
+            .find_map(|line| line.strip_prefix("hackattic_attempts_total "))
+            .unwrap()
+            .parse()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_spent_retry_budget_skips_retries() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/challenges/form_challenge/problem/"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"word": "hi"}"#))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/challenges/form_challenge/solve/"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"rejected": "wrong"}"#))
+            .mount(&server)
+            .await;
+
+        let budget = Arc::new(RetryBudget::new(2));
+        let options = SolveOptions {
+            resubmit_on_reject: 5,
+            retry_budget: Some(Arc::clone(&budget)),
+            ..Default::default()
+        };
+        let context = test_util::context(&server.uri(), Config::default());
+        for _ in 0..2 {
+            let err = solve::<FormChallenge>(reqwest::Client::new(), &context, &options)
+                .await
+                .unwrap_err();
+            assert!(matches!(err, HackatticError::Rejected(_)), "{err:?}");
+        }
+
+        // the first solve spent both retries, the second got none
+        assert_eq!(budget.left(), 0);
+        let posts = server.received_requests().await.unwrap();
+        let posts = posts
+            .iter()
+            .filter(|r| r.method == wiremock::http::Method::Post);
+        assert_eq!(posts.count(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_expired_problem_is_refetched() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/challenges/mini_miner/problem/"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string(r#"{"difficulty": 4, "block": {"data": [], "nonce": null}}"#),
+            )
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/challenges/mini_miner/solve/"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_string(r#"{"rejected": "problem expired"}"#),
+            )
+            .up_to_n_times(1)
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/challenges/mini_miner/solve/"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"passed": true}"#))
+            .mount(&server)
+            .await;
+
+        // mini_miner is never resubmitted, but an expired problem must be
+        let options = SolveOptions {
+            refetch_on_expired: 1,
+            resubmit_on_reject: 3,
+            ..Default::default()
+        };
+        let response = solve::<MiniMiner>(
+            reqwest::Client::new(),
+            &test_util::context(&server.uri(), Config::default()),
+            &options,
+        )
+        .await
+        .unwrap();
+        assert_eq!(response, r#"{"passed": true}"#);
+
+        assert!(is_expired("Problem expired, fetch a new one"));
+        assert!(!is_expired("wrong nonce"));
+    }
+
+    #[tokio::test]
+    async fn test_extra_params_are_sent() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/challenges/form_challenge/problem/"))
+            .and(query_param("variant", "hard"))
+            .and(query_param("access_token", "token"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"word": "hi"}"#))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/challenges/form_challenge/solve/"))
+            .and(query_param("variant", "hard"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("{}"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let options = SolveOptions {
+            extra_params: vec![
+                ("variant".to_string(), "hard".to_string()),
+                ("access_token".to_string(), "stolen".to_string()),
+            ],
+            ..Default::default()
+        };
+        solve::<FormChallenge>(
+            reqwest::Client::new(),
+            &test_util::context(&server.uri(), Config::default()),
+            &options,
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_diff_problems_reports_regenerated_fields() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/challenges/form_challenge/problem/"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_string(r#"{"word": "hi", "size": 3}"#),
+            )
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/challenges/form_challenge/problem/"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_string(r#"{"word": "yo", "size": 3}"#),
+            )
+            .mount(&server)
+            .await;
+
+        let diff = diff_problems(
+            &reqwest::Client::new(),
+            &test_util::context(&server.uri(), Config::default()),
+            "form_challenge",
+            &SolveOptions::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(diff, "- .word: \"hi\"\n+ .word: \"yo\"");
+    }
+
+    struct SlowChallenge;
+
+    impl Hackattic for SlowChallenge {
+        const NAME: &'static str = "form_challenge";
+        type Problem = FormProblem;
+        type Answer = FormAnswer;
+
+        async fn solve(problem: Self::Problem) -> anyhow::Result<Self::Answer> {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            FormChallenge::solve(problem).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_only_solve_phase_deadline_is_exceeded() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/challenges/form_challenge/problem/"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"word": "hi"}"#))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/challenges/form_challenge/solve/"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("{}"))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let options = SolveOptions {
+            deadlines: PhaseDeadlines {
+                fetch: Some(Duration::from_secs(10)),
+                solve: Some(Duration::from_millis(50)),
+                submit: Some(Duration::from_secs(10)),
+            },
+            ..Default::default()
+        };
+        let err = solve::<SlowChallenge>(
+            reqwest::Client::new(),
+            &test_util::context(&server.uri(), Config::default()),
+            &options,
+        )
+        .await
+        .unwrap_err();
+        assert!(
+            matches!(err, HackatticError::TimedOut(Phase::Solve, _)),
+            "{err:?}"
+        );
+        assert_eq!(
+            err.to_string(),
+            "solve phase took longer than its 50ms deadline"
+        );
+    }
+
+    #[test]
+    fn test_slow_submit_is_not_abandoned() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let (result, logs) = capture_logs(|| {
+            runtime.block_on(async {
+                let server = MockServer::start().await;
+                Mock::given(method("GET"))
+                    .and(path("/challenges/form_challenge/problem/"))
+                    .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"word": "hi"}"#))
+                    .mount(&server)
+                    .await;
+                Mock::given(method("POST"))
+                    .and(path("/challenges/form_challenge/solve/"))
+                    .respond_with(
+                        ResponseTemplate::new(200)
+                            .set_body_string("{}")
+                            .set_delay(Duration::from_millis(200)),
+                    )
+                    .expect(1)
+                    .mount(&server)
+                    .await;
+
+                let options = SolveOptions {
+                    deadlines: PhaseDeadlines {
+                        submit: Some(Duration::from_millis(50)),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                };
+                solve::<FormChallenge>(
+                    reqwest::Client::new(),
+                    &test_util::context(&server.uri(), Config::default()),
+                    &options,
+                )
+                .await
+            })
+        });
+        assert_eq!(result.unwrap(), "{}");
+        assert!(logs.contains("past its 50ms deadline"), "{logs}");
+    }
+
+    #[tokio::test]
+    async fn test_answer_rename_applies_to_body() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/challenges/form_challenge/problem/"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"word": "hi"}"#))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/challenges/form_challenge/solve/"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("{}"))
+            .mount(&server)
+            .await;
+
+        let options = SolveOptions {
+            answer_renames: vec![("word".to_string(), "shout".to_string())],
+            ..Default::default()
+        };
+        solve::<FormChallenge>(
+            reqwest::Client::new(),
+            &test_util::context(&server.uri(), Config::default()),
+            &options,
+        )
+        .await
+        .unwrap();
+
+        let requests = server.received_requests().await.unwrap();
+        assert_eq!(requests.last().unwrap().body, b"shout=HI");
+
+        let mut answer = json!({"a": 1, "b": 2});
+        rename_fields(
+            &mut answer,
+            &[
+                ("a".to_string(), "x".to_string()),
+                ("missing".to_string(), "y".to_string()),
+            ],
+        );
+        assert_eq!(answer, json!({"x": 1, "b": 2}));
+    }
+
+    struct GzipChallenge;
+
+    impl Hackattic for GzipChallenge {
+        const NAME: &'static str = "gzip_challenge";
+        const COMPRESS_ANSWER: bool = true;
+        type Problem = FormProblem;
+        type Answer = FormAnswer;
+
+        async fn solve(problem: Self::Problem) -> anyhow::Result<Self::Answer> {
+            FormChallenge::solve(problem).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_compressed_answer_decompresses() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/challenges/gzip_challenge/solve/"))
+            .and(header("content-encoding", "gzip"))
+            .and(header("content-type", "application/json"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("{}"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let answer = FormAnswer {
+            word: "HI".repeat(1000),
+        };
+        submit_answer::<GzipChallenge>(
+            &reqwest::Client::new(),
+            &test_util::context(&server.uri(), Config::default()),
+            &answer,
+            &SolveOptions::default(),
+        )
+        .await
+        .unwrap();
+
+        let requests = server.received_requests().await.unwrap();
+        let body = &requests[0].body;
+        assert!(body.len() < 2000, "{} bytes", body.len());
+        let mut json = String::new();
+        GzDecoder::new(body.as_slice())
+            .read_to_string(&mut json)
+            .unwrap();
+        assert_eq!(json, serde_json::to_string(&answer).unwrap());
+    }
+
+    struct BareChallenge;
+
+    #[derive(Serialize, Deserialize, Debug)]
+    struct BareAnswer(String);
+
+    impl Hackattic for BareChallenge {
+        const NAME: &'static str = "bare_challenge";
+        type Problem = FormProblem;
+        type Answer = BareAnswer;
+
+        async fn solve(problem: Self::Problem) -> anyhow::Result<Self::Answer> {
+            Ok(BareAnswer(problem.word))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bare_answer_is_posted_unwrapped() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/challenges/bare_challenge/problem/"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"word": "value"}"#))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/challenges/bare_challenge/solve/"))
+            .and(header("content-type", "application/json"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("{}"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        solve::<BareChallenge>(
+            reqwest::Client::new(),
+            &test_util::context(&server.uri(), Config::default()),
+            &Default::default(),
+        )
+        .await
+        .unwrap();
+
+        let requests = server.received_requests().await.unwrap();
+        assert_eq!(requests.last().unwrap().body, br#""value""#);
+    }
+
+    #[tokio::test]
+    async fn test_answer_piped_fixture() {
+        let fixture = std::fs::read_to_string("fixtures/mini_miner.json").unwrap();
+        let fixture: serde_json::Value = serde_json::from_str(&fixture).unwrap();
+        let stdin = std::io::Cursor::new(fixture["problem"].to_string());
+
+        let body = read_problem(stdin).unwrap();
+        let context = HackatticContext::offline().unwrap();
+        let ans = answer::<MiniMiner>(&body, &context, &SolveOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(serde_json::to_value(ans).unwrap(), fixture["answer"]);
+
+        let err = read_problem(std::io::Cursor::new(" \n")).unwrap_err();
+        assert_eq!(err.to_string(), "no problem JSON on stdin");
+    }
+
+    #[test]
+    fn test_rejection_reason() {
+        assert_eq!(
+            rejection_reason(r#"{"rejected": "wrong nonce"}"#).as_deref(),
+            Some("wrong nonce")
+        );
+        assert_eq!(
+            rejection_reason(r#"{"error": "unknown challenge"}"#).as_deref(),
+            Some("unknown challenge")
+        );
+        assert_eq!(rejection_reason(r#"{"passed": true}"#), None);
+        assert_eq!(rejection_reason("not json"), None);
+    }
+
+    /// Answers `{"result": "correct"}` or `{"result": "wrong"}`.
+    struct ResultChallenge;
+
+    impl Hackattic for ResultChallenge {
+        const NAME: &'static str = "result_challenge";
+        type Problem = FormProblem;
+        type Answer = FormAnswer;
+
+        async fn solve(problem: Self::Problem) -> anyhow::Result<Self::Answer> {
+            FormChallenge::solve(problem).await
+        }
+
+        fn interpret_response(body: &str) -> anyhow::Result<SolveOutcome> {
+            let value: serde_json::Value = serde_json::from_str(body)?;
+            match value["result"].as_str() {
+                Some("correct") => Ok(SolveOutcome::Accepted(body.to_string())),
+                Some(result) => Ok(SolveOutcome::Rejected(result.to_string())),
+                None => anyhow::bail!("no result in {body}"),
+            }
+        }
+    }
+
+    /// Answers in plain text, `OK` or `FAIL: <reason>`.
+    struct PlainChallenge;
+
+    impl Hackattic for PlainChallenge {
+        const NAME: &'static str = "plain_challenge";
+        type Problem = FormProblem;
+        type Answer = FormAnswer;
+
+        async fn solve(problem: Self::Problem) -> anyhow::Result<Self::Answer> {
+            FormChallenge::solve(problem).await
+        }
+
+        fn interpret_response(body: &str) -> anyhow::Result<SolveOutcome> {
+            Ok(match body.strip_prefix("FAIL: ") {
+                Some(reason) => SolveOutcome::Rejected(reason.to_string()),
+                None => SolveOutcome::Accepted(body.to_string()),
+            })
+        }
+    }
+
+    async fn submit_with_response<T: Hackattic<Answer = FormAnswer>>(
+        response: &str,
+    ) -> Result<String, HackatticError> {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path(format!("/challenges/{}/solve/", T::NAME)))
+            .respond_with(ResponseTemplate::new(200).set_body_string(response))
+            .mount(&server)
+            .await;
+
+        let answer = FormAnswer {
+            word: "HI".to_string(),
+        };
+        submit_answer::<T>(
+            &reqwest::Client::new(),
+            &test_util::context(&server.uri(), Config::default()),
+            &answer,
+            &SolveOptions::default(),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_result_shaped_response() {
+        let body = r#"{"result": "correct"}"#;
+        assert_eq!(
+            submit_with_response::<ResultChallenge>(body).await.unwrap(),
+            body
+        );
+        assert!(matches!(
+            submit_with_response::<ResultChallenge>(r#"{"result": "wrong"}"#).await,
+            Err(HackatticError::Rejected(reason)) if reason == "wrong"
+        ));
+        assert!(matches!(
+            submit_with_response::<ResultChallenge>("{}").await,
+            Err(HackatticError::SolverFailed(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_plain_text_response() {
+        assert_eq!(
+            submit_with_response::<PlainChallenge>("OK").await.unwrap(),
+            "OK"
+        );
+        assert!(matches!(
+            submit_with_response::<PlainChallenge>("FAIL: too slow").await,
+            Err(HackatticError::Rejected(reason)) if reason == "too slow"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_saved_answer_resubmits() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/challenges/mini_miner/solve/"))
+            .and(body_json(json!({"nonce": 10269})))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"passed": true}"#))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let file = std::env::temp_dir().join(format!("answer-{}.json", std::process::id()));
+        save_answer::<MiniMiner>(&file, &MiniMinerAnswer { nonce: 10269 }).unwrap();
+        let ans = load_answer::<MiniMiner>(&file).unwrap();
+        let response = submit_answer::<MiniMiner>(
+            &reqwest::Client::new(),
+            &test_util::context(&server.uri(), Config::default()),
+            &ans,
+            &SolveOptions::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response, r#"{"passed": true}"#);
+
+        // a nonce is no password_hashing answer
+        let err = load_answer::<PasswordHashing>(&file).unwrap_err();
+        std::fs::remove_file(&file).unwrap();
+        assert!(
+            err.to_string()
+                .ends_with("is not a password_hashing answer"),
+            "{err}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_raw_answer_is_posted_verbatim() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/challenges/by_hand/solve/"))
+            .and(header("content-type", "application/json"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"passed": true}"#))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let answer = "{ \"secret\":  \"42\" }\n";
+        submit_raw_answer(
+            &reqwest::Client::new(),
+            &test_util::context(&server.uri(), Config::default()),
+            "by_hand",
+            answer,
+            &SolveOptions::default(),
+        )
+        .await
+        .unwrap();
+
+        let requests = server.received_requests().await.unwrap();
+        assert_eq!(requests[0].body, answer.as_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_client_sends_user_agent_and_env_headers() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(header("user-agent", USER_AGENT))
+            .and(header("x-proxy-token", "secret"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let vars = [
+            ("HA_HEADER_X_PROXY_TOKEN".to_string(), "secret".to_string()),
+            ("PATH".to_string(), "/usr/bin".to_string()),
+        ];
+        let client = client_builder(vars.into_iter()).unwrap().build().unwrap();
+        let resp = client.get(server.uri()).send().await.unwrap();
+        assert!(resp.status().is_success());
+    }
+
+    #[test]
+    fn test_missing_field_fails_schema() {
+        let schema = json!({
+            "type": "object",
+            "required": ["nonce"],
+            "properties": {"nonce": {"type": "integer"}}
+        });
+        check_schema(&schema, &json!({"nonce": 45})).unwrap();
+
+        let err = check_schema(&schema, &json!({"nunce": 45})).unwrap_err();
+        assert!(err.to_string().contains("nonce"), "{err}");
+    }
+
+    #[test]
+    fn test_dump_request_logs_submitted_body() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let (body, logs) = capture_logs(|| {
+            runtime.block_on(async {
+                let server = MockServer::start().await;
+                Mock::given(method("GET"))
+                    .and(path("/challenges/form_challenge/problem/"))
+                    .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"word": "hi"}"#))
+                    .mount(&server)
+                    .await;
+                Mock::given(method("POST"))
+                    .and(path("/challenges/form_challenge/solve/"))
+                    .respond_with(ResponseTemplate::new(200).set_body_string("{}"))
+                    .mount(&server)
+                    .await;
+
+                let options = SolveOptions {
+                    dump_request: true,
+                    ..Default::default()
+                };
+                solve::<FormChallenge>(
+                    reqwest::Client::new(),
+                    &test_util::context(&server.uri(), Config::default()),
+                    &options,
+                )
+                .await
+                .unwrap();
+
+                let requests = server.received_requests().await.unwrap();
+                String::from_utf8(requests.last().unwrap().body.clone()).unwrap()
+            })
+        });
+
+        assert_eq!(body, "word=HI");
+        assert!(logs.contains("POST http://"), "{logs}");
+        assert!(
+            logs.contains("/solve/?access_token=%3Credacted%3E"),
+            "{logs}"
+        );
+        assert!(logs.contains(&format!("\n\n{body}")), "{logs}");
+        let dumped = logs.lines().filter(|line| line.contains(" INFO "));
+        assert!(
+            dumped.clone().all(|line| !line.contains("token=token")),
+            "{logs}"
+        );
+    }
+
+    #[test]
+    fn test_bare_and_wrapped_problems_parse() {
+        for body in [
+            r#"{"word": "hi"}"#,
+            r#"{"problem": {"word": "hi"}}"#,
+            r#"{"data": {"word": "hi"}, "expires_in": 60}"#,
+        ] {
+            let problem: FormProblem = parse_problem("form_challenge", body).unwrap();
+            assert_eq!(problem.word, "hi", "{body}");
+        }
+
+        let err =
+            parse_problem::<FormProblem>("form_challenge", r#"{"problem": {"w": 1}}"#).unwrap_err();
+        assert!(err.to_string().contains("missing field `word`"), "{err}");
+    }
+
+    #[test]
+    fn test_parse_error_names_challenge_and_quotes_body() {
+        let body = "{\n  \"word\": hi\n}";
+        let err = parse_problem::<FormProblem>("form_challenge", body).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unable to parse form_challenge problem (16 bytes): expected value at line 2 column 11, \
+             near `{\\n  \"word\": hi\\n}`"
+        );
+
+        let dump = format!(r#"{{"dump": "{}", oops}}"#, "A".repeat(100_000));
+        let err = parse_problem::<serde_json::Value>("backup_restore", &dump).unwrap_err();
+        let message = err.to_string();
+        assert!(message.len() < 300, "{message}");
+        assert!(message.ends_with(&format!("near `...{}\", oops}}`", "A".repeat(37))));
+    }
+}
@@ -0,0 +1,63 @@
+use anyhow::{Context, Result};
+
+use crate::{
+    hackattic_context::HackatticContext,
+    help_me_unpack::HelpMeUnpack,
+    mini_miner::MiniMiner,
+    password_hashing::PasswordHashing,
+    regress::{check, Fixture},
+    Hackattic,
+};
+
+/// Small worked problems with answers computed independently of the
+/// solvers (Python's `struct` and `hashlib`), built into the binary so the
+/// check runs without the fixtures directory or the network. Challenges
+/// whose answer isn't reproducible or that need a service have none.
+const SAMPLES: &[(&str, &str)] = &[
+    (
+        HelpMeUnpack::NAME,
+        include_str!("../samples/help_me_unpack.json"),
+    ),
+    (MiniMiner::NAME, include_str!("../samples/mini_miner.json")),
+    (
+        PasswordHashing::NAME,
+        include_str!("../samples/password_hashing.json"),
+    ),
+];
+
+pub fn sample(name: &str) -> Result<Fixture> {
+    let (_, json) = SAMPLES
+        .iter()
+        .find(|(sample, _)| *sample == name)
+        .with_context(|| format!("no sample for {name}"))?;
+    serde_json::from_str(json).with_context(|| format!("unable to parse the {name} sample"))
+}
+
+/// Solves the named challenge's sample and compares the answer against the
+/// known one.
+pub async fn check_sample(name: &str) -> Result<()> {
+    let fixture = sample(name)?;
+    let context = HackatticContext::offline()?;
+    match name {
+        HelpMeUnpack::NAME => check::<HelpMeUnpack>(&fixture, &context).await,
+        MiniMiner::NAME => check::<MiniMiner>(&fixture, &context).await,
+        PasswordHashing::NAME => check::<PasswordHashing>(&fixture, &context).await,
+        _ => unreachable!("every sample has a solver"),
+    }
+    .with_context(|| format!("{name} got its sample wrong"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_sample, SAMPLES};
+
+    #[tokio::test]
+    async fn test_samples_pass() {
+        for (name, _) in SAMPLES {
+            check_sample(name).await.unwrap();
+        }
+
+        let err = check_sample("tales_of_ssl").await.unwrap_err();
+        assert_eq!(err.to_string(), "no sample for tales_of_ssl");
+    }
+}
@@ -0,0 +1,136 @@
+//! Wiping of passwords and key material once they're no longer needed. The
+//! `zeroize` crate isn't vendored here, so this covers the few buffer types
+//! the solvers hold secrets in, and the raw problem body they're parsed
+//! from. Copies out of reach, in reqwest's buffers, debug logs or a
+//! `--record` cassette, are out of scope.
+
+use std::{
+    fmt,
+    ops::{Deref, DerefMut},
+    ptr,
+    sync::atomic::{compiler_fence, Ordering},
+};
+
+use serde::{Deserialize, Deserializer};
+
+pub trait Zeroize {
+    fn zeroize(&mut self);
+}
+
+fn wipe(bytes: &mut [u8]) {
+    // volatile so the stores aren't dropped as dead before the free
+    unsafe { wipe_raw(bytes.as_mut_ptr(), bytes.len()) };
+}
+
+/// # Safety
+/// `ptr` must be valid for writes of `len` bytes, initialised or not.
+unsafe fn wipe_raw(ptr: *mut u8, len: usize) {
+    for i in 0..len {
+        ptr::write_volatile(ptr.add(i), 0);
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+/// Wipes the whole allocation, spare capacity included, since truncated
+/// bytes are still there. Copies left behind by earlier reallocations are
+/// out of reach.
+impl Zeroize for Vec<u8> {
+    fn zeroize(&mut self) {
+        self.clear();
+        // clear kept the allocation, all `capacity` bytes of it are writable
+        unsafe { wipe_raw(self.as_mut_ptr(), self.capacity()) };
+    }
+}
+
+impl Zeroize for String {
+    fn zeroize(&mut self) {
+        // all zero bytes are valid UTF-8, and the string is emptied after
+        unsafe { self.as_mut_vec() }.zeroize();
+    }
+}
+
+impl<const N: usize> Zeroize for [u8; N] {
+    fn zeroize(&mut self) {
+        wipe(self);
+    }
+}
+
+/// Holds a secret and zeroes it on drop.
+#[derive(Clone, Default)]
+pub struct Zeroizing<T: Zeroize>(T);
+
+impl<T: Zeroize> Zeroizing<T> {
+    pub fn new(secret: T) -> Self {
+        Zeroizing(secret)
+    }
+}
+
+impl<T: Zeroize> From<T> for Zeroizing<T> {
+    fn from(secret: T) -> Self {
+        Zeroizing(secret)
+    }
+}
+
+impl<T: Zeroize> Deref for Zeroizing<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Zeroize> DerefMut for Zeroizing<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: Zeroize> Drop for Zeroizing<T> {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+// never the secret itself, so logging whatever holds one can't leak it
+impl<T: Zeroize> fmt::Debug for Zeroizing<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Zeroizing(<redacted>)")
+    }
+}
+
+impl<'de, T: Zeroize + Deserialize<'de>> Deserialize<'de> for Zeroizing<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(Zeroizing)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Zeroize, Zeroizing};
+
+    #[test]
+    fn test_buffers_are_wiped() {
+        let mut password = "rosebud".to_string();
+        password.zeroize();
+        assert!(password.is_empty());
+        assert!(password.capacity() >= "rosebud".len());
+
+        let mut key = [0xa5u8; 32];
+        key.zeroize();
+        assert_eq!(key, [0; 32]);
+
+        let secret: Zeroizing<Vec<u8>> = serde_json::from_str("[1, 2, 3]").unwrap();
+        assert_eq!(*secret, [1, 2, 3]);
+        assert_eq!(format!("{secret:?}"), "Zeroizing(<redacted>)");
+
+        // bytes truncated off are still in the allocation until wiped
+        let mut key = vec![0xa5u8; 16];
+        key.truncate(4);
+        key.zeroize();
+        let spare = key.spare_capacity_mut();
+        assert!(spare.len() >= 16);
+        assert!(spare[..16]
+            .iter()
+            .all(|byte| unsafe { byte.assume_init() } == 0));
+    }
+}
@@ -0,0 +1,170 @@
+use std::fmt;
+
+use reqwest::Client;
+
+use crate::{
+    backup_restore::BackupRestore,
+    hackattic_context::HackatticContext,
+    help_me_unpack::HelpMeUnpack,
+    mini_miner::MiniMiner,
+    password_hashing::PasswordHashing,
+    runner::{fetch_problem, problem_url, rejection_reason},
+    tales_of_ssl::TalesOfSsl,
+    Hackattic, HackatticError, SolveOptions,
+};
+
+/// Challenges this crate has solvers for.
+pub const KNOWN_CHALLENGES: &[&str] = &[
+    HelpMeUnpack::NAME,
+    MiniMiner::NAME,
+    PasswordHashing::NAME,
+    TalesOfSsl::NAME,
+    BackupRestore::NAME,
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChallengeStatus {
+    Solved,
+    /// Refused, with the reason hackattic gave.
+    Unsolved(String),
+    /// The problem fetch couldn't tell, with what was found out instead.
+    Unknown(String),
+}
+
+/// Each challenge's status, in the order asked about.
+#[derive(Debug)]
+pub struct StatusReport {
+    pub challenges: Vec<(String, ChallengeStatus)>,
+}
+
+impl fmt::Display for StatusReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, (name, status)) in self.challenges.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            match status {
+                ChallengeStatus::Solved => write!(f, "{name}: solved")?,
+                ChallengeStatus::Unsolved(reason) => write!(f, "{name}: unsolved ({reason})")?,
+                ChallengeStatus::Unknown(reason) => write!(f, "{name}: unknown ({reason})")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Fetches each challenge's problem, there being no progress endpoint. In
+/// playground mode hackattic only hands out problems of challenges the token
+/// has solved, so a problem means solved and a refusal unsolved. Outside it
+/// every challenge has a problem, which says nothing about progress.
+pub async fn fetch_status(
+    client: &Client,
+    context: &HackatticContext,
+    names: &[&str],
+    options: &SolveOptions,
+) -> Result<StatusReport, HackatticError> {
+    let mut challenges = Vec::new();
+    for &name in names {
+        let url = problem_url(&context.base_url, name);
+        let status = match fetch_problem(client, context, name, &url, options).await {
+            Ok(body) => match (rejection_reason(&body), context.playground) {
+                (Some(reason), true) => ChallengeStatus::Unsolved(reason),
+                (None, true) => ChallengeStatus::Solved,
+                (Some(reason), false) => ChallengeStatus::Unknown(reason),
+                (None, false) => ChallengeStatus::Unknown(
+                    "problem available, set HA_PLAYGROUND=1 to tell if solved".to_string(),
+                ),
+            },
+            Err(e @ HackatticError::NotFound(_)) => ChallengeStatus::Unknown(e.to_string()),
+            Err(e) => return Err(e),
+        };
+        challenges.push((name.to_string(), status));
+    }
+    Ok(StatusReport { challenges })
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::{
+        matchers::{method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::{fetch_status, ChallengeStatus};
+    use crate::{config::Config, hackattic_context::HackatticContext, test_util, SolveOptions};
+
+    fn context(server: &MockServer, playground: bool) -> HackatticContext {
+        HackatticContext {
+            playground,
+            ..test_util::context(&server.uri(), Config::default())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mixed_progress() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/challenges/mini_miner/problem/"))
+            .and(query_param("playground", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"difficulty": 8}"#))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/challenges/help_me_unpack/problem/"))
+            .and(query_param("playground", "1"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string(r#"{"error": "solve the challenge first"}"#),
+            )
+            .mount(&server)
+            .await;
+
+        let report = fetch_status(
+            &reqwest::Client::new(),
+            &context(&server, true),
+            &["mini_miner", "help_me_unpack", "retired"],
+            &SolveOptions::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            report.challenges[..2],
+            [
+                ("mini_miner".to_string(), ChallengeStatus::Solved),
+                (
+                    "help_me_unpack".to_string(),
+                    ChallengeStatus::Unsolved("solve the challenge first".to_string())
+                ),
+            ]
+        );
+        assert_eq!(
+            report.to_string(),
+            "mini_miner: solved\n\
+             help_me_unpack: unsolved (solve the challenge first)\n\
+             retired: unknown (not found: retired)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_outside_playground_progress_is_unknown() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/challenges/mini_miner/problem/"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"difficulty": 8}"#))
+            .mount(&server)
+            .await;
+
+        let report = fetch_status(
+            &reqwest::Client::new(),
+            &context(&server, false),
+            &["mini_miner"],
+            &SolveOptions::default(),
+        )
+        .await
+        .unwrap();
+        assert!(matches!(
+            report.challenges[0].1,
+            ChallengeStatus::Unknown(_)
+        ));
+    }
+}
@@ -0,0 +1,74 @@
+use std::{
+    io::Write,
+    sync::{Arc, Mutex},
+};
+
+use serde_json::Value;
+use wiremock::{
+    matchers::{body_json, method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+use crate::{cassette::Cassette, config::Config, hackattic_context::HackatticContext};
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Runs `f` with a subscriber recording everything it logs.
+pub fn capture_logs<R>(f: impl FnOnce() -> R) -> (R, String) {
+    let buffer = Buffer::default();
+    let writer = buffer.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::TRACE)
+        .with_ansi(false)
+        .with_writer(move || writer.clone())
+        .finish();
+
+    let result = tracing::subscriber::with_default(subscriber, f);
+    let logs = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    (result, logs)
+}
+
+/// A context reaching `base_url` with a dummy token, outside playground mode.
+pub fn context(base_url: &str, config: Config) -> HackatticContext {
+    HackatticContext {
+        access_token: "token".to_string(),
+        playground: false,
+        base_url: base_url.to_string(),
+        config,
+    }
+}
+
+/// Mounts the cassette's responses on `server`, each answering its request
+/// once and in the recorded order. A recorded JSON request body must be sent
+/// again unchanged, so a replay also catches answers that differ.
+pub async fn replay(server: &MockServer, cassette: &Cassette) {
+    for interaction in cassette.interactions() {
+        let mut mock =
+            Mock::given(method(interaction.method.as_str())).and(path(interaction.path.as_str()));
+        let recorded_json = interaction
+            .request_body
+            .as_deref()
+            .and_then(|body| serde_json::from_str::<Value>(body).ok());
+        if let Some(body) = recorded_json {
+            mock = mock.and(body_json(body));
+        }
+        mock.respond_with(
+            ResponseTemplate::new(interaction.status).set_body_string(interaction.response_body),
+        )
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(server)
+        .await;
+    }
+}
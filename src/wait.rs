@@ -0,0 +1,76 @@
+use std::{future::Future, time::Duration};
+
+use anyhow::{Context, Result};
+use tokio::time::{sleep, Instant};
+use tracing::debug;
+
+/// Runs `probe` every `interval` until it succeeds, for services that take a
+/// moment to come up. Once `timeout` has passed the last error is returned
+/// with how long and how often the probe was tried.
+pub async fn wait_until<F, Fut, T>(timeout: Duration, interval: Duration, mut probe: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let deadline = Instant::now() + timeout;
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        let err = match probe().await {
+            Ok(value) => return Ok(value),
+            Err(err) => err,
+        };
+        debug!("probe attempt {attempts} failed: {err:#}");
+
+        if Instant::now() + interval > deadline {
+            return Err(err)
+                .with_context(|| format!("not ready after {timeout:?} ({attempts} attempts)"));
+        }
+        sleep(interval).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicU32, Ordering},
+        time::Duration,
+    };
+
+    use super::wait_until;
+
+    #[tokio::test]
+    async fn test_probe_succeeds_on_third_attempt() {
+        let attempts = AtomicU32::new(0);
+        let value = wait_until(
+            Duration::from_secs(1),
+            Duration::from_millis(10),
+            || async {
+                match attempts.fetch_add(1, Ordering::Relaxed) + 1 {
+                    3 => Ok("ready"),
+                    n => anyhow::bail!("attempt {n} not ready"),
+                }
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(value, "ready");
+        assert_eq!(attempts.load(Ordering::Relaxed), 3);
+    }
+
+    #[tokio::test]
+    async fn test_timeout_keeps_last_error() {
+        let err = wait_until(
+            Duration::from_millis(50),
+            Duration::from_millis(10),
+            || async { Err::<(), _>(anyhow::anyhow!("connection refused")) },
+        )
+        .await
+        .unwrap_err();
+
+        let message = format!("{err:#}");
+        assert!(message.starts_with("not ready after 50ms"), "{message}");
+        assert!(message.ends_with("connection refused"), "{message}");
+    }
+}
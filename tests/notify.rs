@@ -0,0 +1,42 @@
+use wiremock::{
+    matchers::{method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+#[tokio::test]
+async fn test_every_attempt_is_notified() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/challenges/help_me_unpack/problem/"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string(r#"{"bytes": "+f///wAoa+7U/gAAAADAPwAAAAAAANA/wAAAAAAAAAA="}"#),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/challenges/help_me_unpack/solve/"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"passed": true}"#))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/hook"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let output = tokio::process::Command::new(env!("CARGO_BIN_EXE_hackattic"))
+        .args(["solve", "help_me_unpack", "--attempts", "2", "--notify-url"])
+        .arg(format!("{}/hook", server.uri()))
+        .env_remove("HA_CONFIG")
+        .env_remove("HA_PLAYGROUND")
+        .env_remove("HA_USE_KEYRING")
+        .env("HA_BASE_URL", server.uri())
+        .env("HA_ACCESS_TOKEN", "token")
+        .output()
+        .await
+        .unwrap();
+
+    assert!(output.status.success(), "{output:?}");
+}
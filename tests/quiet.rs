@@ -0,0 +1,90 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use wiremock::{
+    matchers::{method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+#[test]
+fn test_quiet_prints_only_the_answer() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_hackattic"))
+        .args(["--quiet", "solve", "help_me_unpack", "--stdin"])
+        .env_remove("HA_CONFIG")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(br#"{"bytes": "+f///wAoa+7U/gAAAADAPwAAAAAAANA/wAAAAAAAAAA="}"#)
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "{\"int\":-7,\"uint\":4000000000,\"short\":-300,\"float\":1.5,\"double\":0.25,\"big_endian_double\":-2.0}\n"
+    );
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "");
+}
+
+#[tokio::test]
+async fn test_quiet_prints_only_the_accepted_answer() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/challenges/help_me_unpack/problem/"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string(r#"{"bytes": "+f///wAoa+7U/gAAAADAPwAAAAAAANA/wAAAAAAAAAA="}"#),
+        )
+        .expect(2)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/challenges/help_me_unpack/solve/"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"rejected": "flaky"}"#))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/challenges/help_me_unpack/solve/"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"passed": true}"#))
+        .mount(&server)
+        .await;
+
+    let output = tokio::process::Command::new(env!("CARGO_BIN_EXE_hackattic"))
+        .args([
+            "--quiet",
+            "solve",
+            "help_me_unpack",
+            "--resubmit-on-reject",
+            "1",
+            "--latency-report",
+        ])
+        .env_remove("HA_CONFIG")
+        .env_remove("HA_PLAYGROUND")
+        .env_remove("HA_USE_KEYRING")
+        .env("HA_BASE_URL", server.uri())
+        .env("HA_ACCESS_TOKEN", "token")
+        .output()
+        .await
+        .unwrap();
+
+    assert!(output.status.success(), "{output:?}");
+    // the rejected answer is never printed, only the one that passed
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "{\"int\":-7,\"uint\":4000000000,\"short\":-300,\"float\":1.5,\"double\":0.25,\"big_endian_double\":-2.0}\n"
+    );
+    assert!(
+        !output.stderr.is_empty(),
+        "the latency report goes to stderr"
+    );
+}
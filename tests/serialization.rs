@@ -0,0 +1,53 @@
+use std::sync::Arc;
+
+use hackattic::mini_miner::{bench_serialization, serialize_block, Block, BlockTemplate, Data};
+
+fn block() -> Block {
+    Block {
+        data: Arc::new(vec![
+            Data {
+                data: "plain".to_string(),
+                nonce: 1,
+            },
+            Data {
+                data: "quote \" backslash \\ newline \n é".to_string(),
+                nonce: -44,
+            },
+            Data {
+                data: String::new(),
+                nonce: i32::MIN,
+            },
+        ]),
+        nonce: None,
+    }
+}
+
+#[test]
+fn test_templated_serialization_matches_serde() {
+    for block in [
+        block(),
+        Block {
+            data: Arc::new(vec![]),
+            nonce: None,
+        },
+    ] {
+        let template = BlockTemplate::new(&block);
+        assert_eq!(template.serialize(None), serialize_block(&block));
+
+        let nonces = (0..200_000).chain([-1, -10, i32::MAX, i32::MIN]);
+        for nonce in nonces {
+            assert_eq!(
+                template.serialize(Some(nonce)),
+                serialize_block(&block.with_nonce(nonce)),
+                "nonce {nonce}"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_bench_serialization_agrees() {
+    let bench = bench_serialization(&block(), 10_000).unwrap();
+    assert_eq!(bench.nonces, 10_000);
+    assert!(bench.speedup() > 0.0, "{bench:?}");
+}